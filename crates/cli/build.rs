@@ -0,0 +1,31 @@
+use std::process::Command;
+
+/// Set `DC_VERSION` to the crate version, plus the commit and its date when
+/// building from a git checkout, e.g. `0.0.20 (1a2b3c4 2026-01-02)`.
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    let version = env!("CARGO_PKG_VERSION");
+
+    let version = match git(&["log", "-1", "--format=%h %cs"]) {
+        Some(commit) => {
+            // Rebuild when HEAD moves, whether to another branch or commit.
+            for path in ["HEAD", "refs/heads", "packed-refs"] {
+                if let Some(path) = git(&["rev-parse", "--git-path", path]) {
+                    println!("cargo:rerun-if-changed={path}");
+                }
+            }
+            format!("{version} ({commit})")
+        }
+        // A crates.io or nix source tarball.
+        None => version.to_owned(),
+    };
+    println!("cargo:rustc-env=DC_VERSION={version}");
+}
+
+fn git(args: &[&str]) -> Option<String> {
+    let out = Command::new("git").args(args).output().ok()?;
+    let out = String::from_utf8(out.stdout)
+        .ok()
+        .filter(|_| out.status.success())?;
+    Some(out.trim().to_owned()).filter(|s| !s.is_empty())
+}
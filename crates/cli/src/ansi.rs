@@ -9,3 +9,26 @@ pub(crate) const YELLOW: SetForegroundColor = SetForegroundColor(Color::Yellow);
 pub(crate) const BLUE: SetForegroundColor = SetForegroundColor(Color::Blue);
 pub(crate) const MAGENTA: SetForegroundColor = SetForegroundColor(Color::Magenta);
 pub(crate) const CYAN: SetForegroundColor = SetForegroundColor(Color::Cyan);
+
+/// Whether to emit color: off when `NO_COLOR` is set or `--no-color` is given.
+pub(crate) fn color_enabled() -> bool {
+    !crossterm::style::Colored::ansi_color_disabled_memoized()
+}
+
+/// Drop ANSI escape sequences, keeping only the visible text.
+pub(crate) fn strip_ansi(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\u{1b}' {
+            for next in chars.by_ref() {
+                if next.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+            continue;
+        }
+        out.push(c);
+    }
+    out
+}
@@ -1,34 +1,65 @@
 use std::io::{BufRead, Write};
+use std::path::PathBuf;
 
 use clap::{Parser, Subcommand};
 use clap_complete::engine::ArgValueCompleter;
 
-use crate::{complete, state::State, workspace::Workspace};
+use crate::{
+    complete, config::Config, preflight, state::State, subscriber::Progress, workspace::Workspace,
+};
 
+mod attach;
+mod bundle;
+mod completions;
 mod compose;
-mod destroy;
+pub(crate) mod destroy;
+mod diff;
+mod down;
 mod exec;
+mod export;
 pub(crate) mod fwd;
 mod go;
 pub(crate) mod proxy;
+mod prune;
 mod show;
 mod status;
-mod up;
+pub(crate) mod up;
 
 const ABOUT: &str =
     "A tool for managing devcontainers, especially when combined with git worktrees";
 
+/// The crate version, with the commit it was built from, if known.
+pub(crate) const VERSION: &str = env!("DC_VERSION");
+
 #[derive(Debug, Parser)]
-#[command(version, about = ABOUT)]
+#[command(version = VERSION, about = ABOUT)]
 pub(crate) struct Cli {
     #[arg(
         short,
         long,
-        help = "name of project [default: The DC_PROJECT variable, then the first configured project]",
+        global = true,
+        help = "name of project, or a path in its repository [default: the DC_PROJECT variable, then the project of the current repository, then the first configured project]",
         add = ArgValueCompleter::new(complete::complete_project),
     )]
     pub(crate) project: Option<String>,
 
+    /// Read config.toml from this path instead, or from stdin for `-` [default: the DC_CONFIG
+    /// variable, then the config directory]
+    #[arg(long, global = true, value_name = "PATH")]
+    pub(crate) config: Option<PathBuf>,
+
+    /// Disable colored output (also disabled by setting NO_COLOR)
+    #[arg(long, global = true)]
+    pub(crate) no_color: bool,
+
+    /// How to report progress on stderr; `json` prints one event per line, for tools wrapping dc
+    #[arg(long, global = true, value_enum, default_value_t)]
+    pub(crate) progress: Progress,
+
+    /// Only print warnings, errors, and command output, not what dc is doing or how long it took
+    #[arg(short, long, global = true)]
+    pub(crate) quiet: bool,
+
     #[command(subcommand)]
     pub(crate) command: Commands,
 }
@@ -43,32 +74,70 @@ pub(crate) enum Commands {
     Fwd(fwd::Fwd),
     #[command(visible_alias = "c")]
     Compose(compose::Compose),
+    Down(down::Down),
     #[command()]
     Destroy(destroy::Destroy),
+    Diff(diff::Diff),
+    Attach(attach::Attach),
+    Bundle(bundle::Bundle),
+    Export(export::Export),
+    Prune(prune::Prune),
     Show(show::Show),
     #[command(visible_alias = "s")]
     Status(status::Status),
     #[command()]
     Go(go::Go),
     Proxy(proxy::Proxy),
+    Completions(completions::Completions),
 }
 
-/// Check that the workspace is safe to tear down (clean git).
+/// Check that the workspace is safe to tear down (clean git, nothing unpushed).
 pub(crate) async fn safety_check(workspace: &Workspace<'_>, force: bool) -> eyre::Result<()> {
     if force {
         return Ok(());
     }
 
-    if workspace.is_dirty().await? {
+    let status = workspace.git_status().await?;
+    if status.is_dirty() {
         eyre::bail!(
             "workspace '{}' has uncommitted changes (use --force to override)",
             workspace.name
         );
     }
+    if status.unpushed > 0 {
+        eyre::bail!(
+            "workspace '{}' has {} unpushed commit(s) (use --force to override)",
+            workspace.name,
+            status.unpushed
+        );
+    }
 
     Ok(())
 }
 
+impl Commands {
+    /// Whether this command talks to Docker (when the project has a devcontainer). Those that do
+    /// run the [`preflight`] checks first, so a broken setup is reported up front.
+    fn needs_docker(&self) -> bool {
+        match self {
+            Commands::Exec(_)
+            | Commands::Fwd(_)
+            | Commands::Compose(_)
+            | Commands::Down(_)
+            | Commands::Destroy(_)
+            | Commands::Diff(_)
+            | Commands::Attach(_)
+            | Commands::Bundle(_)
+            | Commands::Export(_)
+            | Commands::Status(_)
+            | Commands::Proxy(_) => true,
+            Commands::Up(up) => up.needs_docker(),
+            Commands::Show(show) => show.needs_docker(),
+            Commands::Go(_) | Commands::Prune(_) | Commands::Completions(_) => false,
+        }
+    }
+}
+
 pub(crate) fn confirm() -> eyre::Result<bool> {
     eprint!("Proceed? [y/N] ");
     std::io::stderr().flush()?;
@@ -79,16 +148,62 @@ pub(crate) fn confirm() -> eyre::Result<bool> {
 
 impl Cli {
     pub(crate) async fn run(self) -> eyre::Result<()> {
-        match self.command {
-            Commands::Up(up) => up.run(self.project).await,
-            Commands::Exec(exec) => exec.run(self.project).await,
-            Commands::Fwd(fwd) => fwd.run(self.project).await,
-            Commands::Compose(compose) => compose.run(self.project).await,
-            Commands::Show(show) => show.run(self.project).await,
-            Commands::Status(status) => status.run(self.project).await,
-            Commands::Destroy(destroy) => destroy.run(self.project).await,
-            Commands::Go(go) => go.run(self.project).await,
-            Commands::Proxy(proxy) => proxy.run(self.project).await,
+        if self.no_color {
+            crossterm::style::force_color_output(false);
+        }
+
+        // Everything but these works within a single project.
+        let command = match self.command {
+            Commands::Completions(completions) => return completions.run(),
+            Commands::Proxy(proxy) => {
+                return proxy.run(self.project, self.config.as_deref()).await;
+            }
+            command => command,
+        };
+
+        let mut config = Config::load(self.config.as_deref())?;
+        let project = config.resolve_project_arg(self.project)?;
+        let state = State::new(project, &config).await?;
+        if command.needs_docker()
+            && let Some(devcontainer) = &state.devcontainer
+        {
+            preflight::check(&devcontainer.docker).await?;
+        }
+        match command {
+            Commands::Up(up) => up.run(state).await,
+            Commands::Exec(exec) => exec.run(state).await,
+            Commands::Fwd(fwd) => fwd.run(state).await,
+            Commands::Compose(compose) => compose.run(state).await,
+            Commands::Show(show) => show.run(state).await,
+            Commands::Status(status) => status.run(state).await,
+            Commands::Down(down) => down.run(state).await,
+            Commands::Destroy(destroy) => destroy.run(state).await,
+            Commands::Diff(diff) => diff.run(state).await,
+            Commands::Attach(attach) => attach.run(state).await,
+            Commands::Bundle(bundle) => bundle.run(state).await,
+            Commands::Export(export) => export.run(state).await,
+            Commands::Prune(prune) => prune.run(state).await,
+            Commands::Go(go) => go.run(state).await,
+            Commands::Proxy(_) | Commands::Completions(_) => unreachable!("handled above"),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn project_is_accepted_after_the_subcommand() {
+        let before = Cli::try_parse_from(["dc", "-p", "foo", "fwd"]).unwrap();
+        let after = Cli::try_parse_from(["dc", "fwd", "--project", "foo"]).unwrap();
+        assert_eq!(before.project.as_deref(), Some("foo"));
+        assert_eq!(after.project.as_deref(), Some("foo"));
+    }
+
+    #[test]
+    fn no_color_is_accepted_after_the_subcommand() {
+        let cli = Cli::try_parse_from(["dc", "status", "--no-color"]).unwrap();
+        assert!(cli.no_color);
+    }
+}
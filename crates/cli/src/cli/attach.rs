@@ -0,0 +1,55 @@
+use std::process::Command;
+
+use clap::Args;
+use clap_complete::ArgValueCompleter;
+use docker::ContainerStatus;
+use eyre::{WrapErr, eyre};
+
+use crate::cli::State;
+use crate::complete::complete_workspace;
+
+/// Print a running workspace's container ID and workspace folder, or run the configured
+/// `attachCommand` with them
+#[derive(Debug, Args)]
+pub(crate) struct Attach {
+    /// Workspace name [default: current working directory]
+    #[arg(add = ArgValueCompleter::new(complete_workspace))]
+    workspace: Option<String>,
+
+    /// Only print, even if `attachCommand` is configured
+    #[arg(long)]
+    print: bool,
+}
+
+impl Attach {
+    pub(crate) async fn run(self, state: State<'_>) -> eyre::Result<()> {
+        let workspace = state.resolve_workspace(self.workspace).await?;
+        let devcontainer = state.devcontainer_for(&workspace.path)?;
+        let workspace_full = workspace.devcontainer(&devcontainer).await?;
+        if workspace_full.status() != Some(ContainerStatus::Running) {
+            return Err(eyre!("workspace is not running: {}", workspace.name));
+        }
+        let container_id = workspace_full.service_container_id()?;
+        let folder = &devcontainer.config.workspace_folder;
+
+        let attach_command = devcontainer.devconcurrent().attach_command.as_ref();
+        let Some(attach_command) = attach_command.filter(|_| !self.print) else {
+            println!("{container_id}\t{}", folder.display());
+            return Ok(());
+        };
+
+        let args = attach_command.as_args();
+        let status = Command::new(args[0])
+            .args(&args[1..])
+            .env("DC_CONTAINER_ID", container_id)
+            .env("DC_WORKSPACE_FOLDER", folder)
+            .env("DC_WORKSPACE", &workspace.name)
+            .current_dir(&workspace.path)
+            .status()
+            .wrap_err("failed to run attachCommand")?;
+        if !status.success() {
+            return Err(eyre!("attachCommand failed: {status}"));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,132 @@
+use std::fmt::Write as _;
+use std::path::PathBuf;
+
+use clap::Args;
+use clap_complete::ArgValueCompleter;
+use crossterm::style::Stylize;
+use eyre::WrapErr;
+
+use crate::ansi::strip_ansi;
+use crate::cli::State;
+use crate::complete::complete_workspace;
+use crate::docker::compose::{compose_cmd, container_diff};
+use crate::redact::redact;
+
+/// Collect a workspace's status, config, forwards, and recent logs into one markdown file, for bug
+/// reports
+#[derive(Debug, Args)]
+pub(crate) struct Bundle {
+    /// Workspace name [default: current working directory]
+    #[arg(add = ArgValueCompleter::new(complete_workspace))]
+    workspace: Option<String>,
+
+    /// File to write [default: `<workspace>-diagnostics.md`]
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Lines of logs to include from each container
+    #[arg(long, default_value_t = 200)]
+    tail: usize,
+}
+
+impl Bundle {
+    pub(crate) async fn run(self, state: State<'_>) -> eyre::Result<()> {
+        let workspace = state.resolve_workspace(self.workspace).await?;
+        let devcontainer = state.devcontainer_for(&workspace.path)?;
+        // Registers the secrets' values, so they're masked below.
+        devcontainer.config.secret_env(&state.project.path)?;
+
+        let mut s = format!(
+            "# {}\n\n* devconcurrent {}\n* project: {}\n* path: {}\n* collected: {}\n",
+            workspace.name,
+            crate::cli::VERSION,
+            state.project_name,
+            workspace.path.display(),
+            jiff::Timestamp::now(),
+        );
+
+        s.push_str("\n## Containers\n\n");
+        let containers = devcontainer
+            .docker
+            .workspace_container_info(&workspace)
+            .await?;
+        if containers.is_empty() {
+            s.push_str("None.\n");
+        }
+        for c in &containers {
+            writeln!(
+                s,
+                "* {} `{}`: {}",
+                c.service.as_deref().unwrap_or("?"),
+                c.id,
+                c.state
+            )?;
+        }
+
+        s.push_str("\n## Forwarded ports\n\n");
+        let ports = devcontainer
+            .docker
+            .workspace_forwarded_ports(&workspace)
+            .await?;
+        if ports.is_empty() {
+            s.push_str("None.\n");
+        }
+        for port in ports {
+            writeln!(s, "* {port}")?;
+        }
+
+        if let Ok(id) = workspace
+            .devcontainer(&devcontainer)
+            .await?
+            .service_container_id()
+        {
+            let details = devcontainer
+                .docker
+                .client()
+                .await?
+                .inspect_container(id)
+                .await?;
+            s.push_str("\n## Drift from devcontainer.json\n\n");
+            let diff = container_diff(&devcontainer, &workspace, &details)?;
+            if diff.is_empty() {
+                s.push_str("None.\n");
+            }
+            for change in diff {
+                writeln!(s, "* {change}")?;
+            }
+        }
+
+        writeln!(
+            s,
+            "\n## Config\n\n```json\n{}\n```",
+            serde_json::to_string_pretty(&devcontainer.config)?
+        )?;
+
+        let mut logs = compose_cmd(&devcontainer, &workspace)?;
+        logs.args(["logs", "--no-color", "--timestamps", "--tail"])
+            .arg(self.tail.to_string());
+        let out = logs
+            .output()
+            .await
+            .wrap_err("failed to run docker compose logs")?;
+        writeln!(
+            s,
+            "\n## Logs\n\n```\n{}{}```",
+            String::from_utf8_lossy(&out.stdout),
+            String::from_utf8_lossy(&out.stderr),
+        )?;
+
+        let output = self
+            .output
+            .unwrap_or_else(|| PathBuf::from(format!("{}-diagnostics.md", workspace.name)));
+        std::fs::write(&output, strip_ansi(&redact(&s)))
+            .wrap_err_with(|| format!("failed to write {}", output.display()))?;
+        eprintln!(
+            "{} wrote diagnostics for {} to {}; secrets are masked, but check it before sharing",
+            "✓".green(),
+            workspace.name,
+            output.display()
+        );
+        Ok(())
+    }
+}
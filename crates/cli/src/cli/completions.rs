@@ -0,0 +1,36 @@
+use clap::{Args, CommandFactory};
+use clap_complete::Shell;
+use clap_complete::env::Shells;
+
+use crate::cli::Cli;
+
+/// Print shell completions and the `dc` wrapper function, to source from your shell config
+#[derive(Debug, Args)]
+pub(crate) struct Completions {
+    /// The shell to generate for
+    shell: Shell,
+}
+
+impl Completions {
+    /// Equivalent to `COMPLETE=<shell> devconcurrent`: the completions stay dynamic, so they
+    /// still know your projects and workspaces.
+    pub(crate) fn run(self) -> eyre::Result<()> {
+        let shell = self.shell.to_string();
+        let shells = Shells::builtins();
+        let Some(completer) = shells.completer(&shell) else {
+            eyre::bail!("unsupported shell {shell}");
+        };
+
+        let bin_os = std::env::args_os()
+            .next()
+            .unwrap_or_else(|| "devconcurrent".into());
+        let bin = bin_os.to_string_lossy();
+        let name = Cli::command().get_name().to_owned();
+        completer.write_registration("COMPLETE", &name, &name, &bin, &mut std::io::stdout())?;
+
+        if let Err(e) = crate::register_shell_function(&shell) {
+            tracing::warn!("Failed to generate shell wrapper: {e}");
+        }
+        Ok(())
+    }
+}
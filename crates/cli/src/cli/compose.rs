@@ -5,7 +5,6 @@ use clap_complete::engine::ArgValueCompleter;
 
 use crate::cli::State;
 use crate::complete::{self, complete_workspace};
-use crate::config::Config;
 use crate::docker::compose::compose_cmd;
 
 /// Run `docker compose` against the given workspace
@@ -21,15 +20,13 @@ pub(crate) struct Compose {
 }
 
 impl Compose {
-    pub(crate) async fn run(self, project: Option<String>) -> eyre::Result<()> {
-        let config = Config::load()?;
-        let state = State::new(project, &config).await?;
+    pub(crate) async fn run(self, state: State<'_>) -> eyre::Result<()> {
         let workspace = state.resolve_workspace(self.workspace).await?;
         let devcontainer = state.devcontainer_for(&workspace.path)?;
 
         let mut cmd = compose_cmd(&devcontainer, &workspace)?;
         cmd.args(&self.args);
 
-        Err(cmd.into_std().exec().into())
+        Err(cmd.into_inner().into_std().exec().into())
     }
 }
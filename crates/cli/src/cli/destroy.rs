@@ -2,35 +2,46 @@ use std::borrow::Cow;
 
 use clap::Args;
 use clap_complete::ArgValueCompleter;
-use docker::{PROJECT_LABEL, WORKSPACE_LABEL};
+use docker::{MANAGED_LABEL, PROJECT_LABEL, WORKSPACE_LABEL};
 use eyre::eyre;
 
 use crate::ansi::{RED, RESET, YELLOW};
 use crate::cli::{State, confirm, safety_check};
 use crate::complete::complete_workspace;
-use crate::config::Config;
-use crate::docker::compose::{compose_cmd, remove_override_file};
+use crate::docker::compose::{compose_cmd_for, remove_override_file};
 use crate::run::{self, Runnable, Runner, run_command};
 use crate::state::DevcontainerState;
 use crate::workspace::Workspace;
+use crate::workspace::metadata::Metadata;
 
 /// Fully destroy the workspace; equivalent to `docker compose down -v --rmi local --remove-orphans && git worktree remove`
 #[derive(Debug, Args)]
 pub(crate) struct Destroy {
-    /// Workspace name
-    #[arg(add = ArgValueCompleter::new(complete_workspace))]
+    /// Workspace name, exactly; no prefix or fuzzy matching
+    #[arg(add = ArgValueCompleter::new(complete_workspace), conflicts_with = "all")]
     workspace: Option<String>,
 
     /// Force remove the worktree, even if dirty
     #[arg(short, long)]
     force: bool,
+
+    /// Destroy every workspace in the project in parallel, even if dirty
+    #[arg(short, long)]
+    all: bool,
+
+    /// With `--all`, also destroy the root workspace
+    #[arg(long, requires = "all")]
+    include_root: bool,
 }
 
 impl Destroy {
-    pub(crate) async fn run(self, project: Option<String>) -> eyre::Result<()> {
-        let config = Config::load()?;
-        let state = State::new(project, &config).await?;
-        let workspace = state.resolve_workspace(self.workspace).await?;
+    pub(crate) async fn run(self, state: State<'_>) -> eyre::Result<()> {
+        if self.all {
+            return destroy_all(&state, self.include_root, state.config.confirm_threshold).await;
+        }
+
+        // Exact names only: `destroy -f fxlg` shouldn't quietly take out `fix-login`.
+        let workspace = state.resolve_exact_workspace(self.workspace).await?;
         let devcontainer = state.devcontainer_for(&workspace.path).ok();
 
         if !workspace.path.exists() {
@@ -59,6 +70,94 @@ impl Destroy {
     }
 }
 
+async fn destroy_all(
+    state: &State<'_>,
+    include_root: bool,
+    confirm_threshold: Option<usize>,
+) -> eyre::Result<()> {
+    let mut workspaces = Workspace::list(state).await?;
+    workspaces.retain(|ws| include_root || !ws.is_root);
+    if workspaces.is_empty() {
+        eprintln!("No workspaces to destroy.");
+        return Ok(());
+    }
+    workspaces.sort_by(|a, b| b.is_root.cmp(&a.is_root).then_with(|| a.name.cmp(&b.name)));
+
+    eprintln!(
+        "{YELLOW}Will destroy {} workspace(s) in project '{}', even if dirty:{RESET}",
+        workspaces.len(),
+        state.project_name
+    );
+    for ws in &workspaces {
+        eprintln!("  {} ({})", ws.name, ws.path.display());
+    }
+    if include_root {
+        eprintln!(
+            "{YELLOW}This includes the {RED}root{YELLOW} workspace — DATA WILL BE LOST{RESET}",
+        );
+    }
+
+    let devcontainers: Vec<Option<DevcontainerState>> = workspaces
+        .iter()
+        .map(|ws| state.devcontainer_for(&ws.path).ok())
+        .collect();
+
+    let below_threshold = confirm_threshold.is_some_and(|n| workspaces.len() <= n);
+    let needs_confirm =
+        include_root || !below_threshold || any_at_risk(&workspaces, &devcontainers).await?;
+    if needs_confirm && !confirm()? {
+        eprintln!("Aborted.");
+        return Ok(());
+    }
+    let cleanups = workspaces
+        .iter()
+        .zip(&devcontainers)
+        .map(|(workspace, devcontainer)| Cleanup {
+            devcontainer: devcontainer.as_ref(),
+            workspace,
+            force: true,
+        });
+
+    Runner::run_parallel("destroy", cleanups).await
+}
+
+/// Whether any workspace has work we'd lose (dirty or unpushed) or is in use
+/// (has running execs).
+async fn any_at_risk(
+    workspaces: &[Workspace<'_>],
+    devcontainers: &[Option<DevcontainerState>],
+) -> eyre::Result<bool> {
+    for (workspace, devcontainer) in workspaces.iter().zip(devcontainers) {
+        let status = workspace.git_status().await?;
+        if status.is_dirty() || status.unpushed > 0 {
+            return Ok(true);
+        }
+        if let Some(devcontainer) = devcontainer
+            && workspace
+                .devcontainer(devcontainer)
+                .await?
+                .has_execs(&devcontainer.docker)
+                .await
+        {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Tear down a workspace that `up` was creating when it was interrupted.
+pub(crate) async fn destroy_new(
+    devcontainer: Option<&DevcontainerState>,
+    workspace: &Workspace<'_>,
+) -> eyre::Result<()> {
+    Runner::run(Cleanup {
+        devcontainer,
+        workspace,
+        force: true,
+    })
+    .await
+}
+
 struct Cleanup<'a> {
     devcontainer: Option<&'a DevcontainerState>,
     workspace: &'a Workspace<'a>,
@@ -76,17 +175,26 @@ impl Runnable for Cleanup<'_> {
 
     async fn run(self, _: run::Token) -> eyre::Result<()> {
         if let Some(devcontainer) = self.devcontainer {
-            let mut down_cmd = compose_cmd(devcontainer, self.workspace)?;
+            // Prefer the compose project recorded at creation, in case naming has changed since.
+            let compose_project = Metadata::read(&self.workspace.path).map_or_else(
+                || self.workspace.compose_project_name(),
+                |m| m.compose_project,
+            );
+            let mut down_cmd = compose_cmd_for(devcontainer, self.workspace, &compose_project)?;
             down_cmd.args(["down", "-v", "--rmi", "local", "--remove-orphans"]);
 
-            run_command(down_cmd).await?;
+            run_command(down_cmd.into_inner()).await?;
             remove_override_file(self.workspace);
 
             // Remove any port-forward sidecars targeting this workspace
-            let client = &devcontainer.docker.client;
+            let client = devcontainer.docker.client().await?;
             if let Ok(summaries) = client
                 .list_containers()
                 .all(true)
+                .with_label(
+                    MANAGED_LABEL,
+                    self.workspace.state.config.managed_label_value(),
+                )
                 .with_label(PROJECT_LABEL, self.workspace.state.project_name.as_str())
                 .with_label(WORKSPACE_LABEL, self.workspace.name.as_str())
                 .call()
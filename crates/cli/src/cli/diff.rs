@@ -0,0 +1,45 @@
+use clap::Args;
+use clap_complete::ArgValueCompleter;
+use crossterm::style::Stylize;
+
+use crate::cli::State;
+use crate::complete::complete_workspace;
+use crate::docker::compose::container_diff;
+
+/// Show how a workspace's container differs from its devcontainer.json
+#[derive(Debug, Args)]
+pub(crate) struct Diff {
+    /// Workspace name [default: current working directory]
+    #[arg(add = ArgValueCompleter::new(complete_workspace))]
+    workspace: Option<String>,
+}
+
+impl Diff {
+    pub(crate) async fn run(self, state: State<'_>) -> eyre::Result<()> {
+        let workspace = state.resolve_workspace(self.workspace).await?;
+        let devcontainer = state.devcontainer_for(&workspace.path)?;
+        let workspace_full = workspace.devcontainer(&devcontainer).await?;
+        let container_id = workspace_full.service_container_id()?;
+        let details = devcontainer
+            .docker
+            .client()
+            .await?
+            .inspect_container(container_id)
+            .await?;
+
+        let diff = container_diff(&devcontainer, &workspace, &details)?;
+        if diff.is_empty() {
+            eprintln!(
+                "{} {} matches devcontainer.json",
+                "✓".green(),
+                workspace.name
+            );
+            return Ok(());
+        }
+        for change in &diff {
+            println!("{change}");
+        }
+        eprintln!("run `dc up --recreate` to apply devcontainer.json");
+        Ok(())
+    }
+}
@@ -0,0 +1,48 @@
+use clap::Args;
+use clap_complete::ArgValueCompleter;
+
+use crate::cli::State;
+use crate::complete::complete_workspace;
+use crate::devcontainer::ComposeShutdownAction;
+use crate::docker::compose::compose_cmd;
+use crate::run::Runner;
+use crate::run::cmd::NamedCmd;
+
+/// Shut a workspace down as its `shutdownAction` says, keeping its containers, volumes, and
+/// worktree for the next `dc up`
+#[derive(Debug, Args)]
+pub(crate) struct Down {
+    /// Workspace name [default: current working directory]
+    #[arg(add = ArgValueCompleter::new(complete_workspace))]
+    workspace: Option<String>,
+}
+
+impl Down {
+    pub(crate) async fn run(self, state: State<'_>) -> eyre::Result<()> {
+        let workspace = state.resolve_workspace(self.workspace).await?;
+        let devcontainer = state.devcontainer_for(&workspace.path)?;
+
+        match devcontainer.config.shutdown_action {
+            ComposeShutdownAction::None => {
+                eprintln!(
+                    "shutdownAction is \"none\"; leaving {} running",
+                    workspace.name
+                );
+                Ok(())
+            }
+            ComposeShutdownAction::StopCompose => {
+                let mut stop = compose_cmd(&devcontainer, &workspace)?;
+                stop.arg("stop");
+                let stop = stop.into_inner().as_std().into();
+                Runner::run(NamedCmd {
+                    name: "docker compose stop",
+                    cmd: &stop,
+                    dir: None,
+                })
+                .await?;
+                eprintln!("Stopped {}", workspace.name);
+                Ok(())
+            }
+        }
+    }
+}
@@ -1,17 +1,21 @@
-use std::io::IsTerminal;
+use std::fs::File;
+use std::io::{IsTerminal, Read, Seek};
 use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
 
 use clap::Args;
 use clap_complete::ArgValueCompleter;
+use color_eyre::Section;
+use crossterm::style::Stylize;
 use docker::ContainerStatus;
-use eyre::eyre;
+use eyre::{WrapErr, eyre};
 use indexmap::IndexMap;
 
 use crate::cli::State;
 use crate::complete::complete_workspace;
-use crate::config::Config;
 use crate::devcontainer::substitution;
 use crate::docker::probe;
+use crate::helpers::read_env_file;
 use crate::state::DevcontainerState;
 
 /// Exec into a running devcontainer
@@ -21,15 +25,54 @@ pub(crate) struct Exec {
     #[arg(short, long, add = ArgValueCompleter::new(complete_workspace))]
     workspace: Option<String>,
 
-    /// command to run [default: Configured defaultExec]
+    /// Read `KEY=VALUE` lines from this file into the environment, overriding `remoteEnv`
+    #[arg(long)]
+    env_file: Option<PathBuf>,
+
+    /// Working directory in the container; relative paths are taken from the configured
+    /// `workspaceFolder` [default: workspaceFolder]
+    #[arg(long)]
+    workspace_folder: Option<PathBuf>,
+
+    /// Run this host script in the container, using its shebang interpreter [default: sh]
+    #[arg(long, conflicts_with = "cmd")]
+    script: Option<PathBuf>,
+
+    /// Don't allocate a TTY, even if stdin is a terminal
+    #[arg(short = 'T', long)]
+    no_tty: bool,
+
+    /// Clear the screen before attaching, for a clean start after noisy output
+    #[arg(long, conflicts_with_all = ["no_tty", "script", "detach"])]
+    clear: bool,
+
+    /// Start the command in the background and return immediately
+    #[arg(short, long, conflicts_with_all = ["script", "no_tty"])]
+    detach: bool,
+
+    /// Kill a running exec by the PID `dc show execs` lists, instead of running a command; needs
+    /// the Docker daemon to run on this machine, not in a VM or remotely
+    #[arg(long, value_name = "PID", conflicts_with_all = ["cmd", "script", "detach"])]
+    kill: Option<u32>,
+
+    /// command to run [default: Configured defaultExec, else a login shell]
     #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
     cmd: Vec<String>,
 }
 
+/// Where the command run by [`exec_interactive`] reads its stdin from.
+pub(crate) enum ExecInput {
+    /// Our own stdin, with a TTY when it's a terminal and `tty` is set. With `clear`, the screen is
+    /// cleared first, if we do get a TTY.
+    Inherit { tty: bool, clear: bool },
+    /// A script, piped to the interpreter's stdin.
+    Script(File),
+    /// None; the command runs in the background (`docker exec -d`).
+    Detach,
+}
+
 impl Exec {
-    pub(crate) async fn run(self, project: Option<String>) -> eyre::Result<()> {
-        let config = Config::load()?;
-        let state = State::new(project, &config).await?;
+    pub(crate) async fn run(self, state: State<'_>) -> eyre::Result<()> {
         let workspace = state.resolve_workspace(self.workspace).await?;
         let devcontainer = state.devcontainer_for(&workspace.path)?;
         let devcontainer = &devcontainer;
@@ -41,8 +84,12 @@ impl Exec {
             ));
         }
         let container_id = workspace_full.service_container_id()?;
+        if let Some(pid) = self.kill {
+            return kill(devcontainer, container_id, pid).await;
+        }
         let container =
-            probe::ContainerData::inspect(&devcontainer.docker.client, container_id).await?;
+            probe::ContainerData::inspect(devcontainer.docker.client().await?, container_id)
+                .await?;
         let probed = probe::user_env(
             container_id,
             devcontainer.config.remote_user.as_deref(),
@@ -53,26 +100,148 @@ impl Exec {
         let context =
             substitution::Context::new(&workspace.path, &devcontainer.config.workspace_folder)
                 .with_container(container);
-        let mut remote_env: IndexMap<String, Option<String>> =
-            probed.into_iter().map(|(k, v)| (k, Some(v))).collect();
-        for (key, template) in &devcontainer.config.remote_env {
-            remote_env.insert(key.clone(), template.as_ref().map(|t| t.render(&context)));
+        let mut remote_env = devcontainer.config.exec_env(probed, &context);
+        if let Some(path) = &self.env_file {
+            for (key, value) in read_env_file(path)? {
+                remote_env.insert(key, Some(value));
+            }
         }
 
-        exec_interactive(container_id, devcontainer, &remote_env, &self.cmd)
+        let secrets = devcontainer.config.secret_env(&state.project.path)?;
+
+        let (input, cmd) = match &self.script {
+            Some(path) => {
+                let mut file = File::open(path)
+                    .wrap_err_with(|| format!("failed to open {}", path.display()))?;
+                let mut contents = String::new();
+                file.read_to_string(&mut contents)?;
+                file.rewind()?;
+                (ExecInput::Script(file), script_interpreter(&contents))
+            }
+            None if self.detach => (ExecInput::Detach, self.cmd),
+            None => (
+                ExecInput::Inherit {
+                    tty: !self.no_tty,
+                    clear: self.clear,
+                },
+                self.cmd,
+            ),
+        };
+
+        // `join` keeps absolute paths as they are.
+        let workdir = match &self.workspace_folder {
+            Some(dir) => devcontainer.config.workspace_folder.join(dir),
+            None => devcontainer.config.workspace_folder.clone(),
+        };
+
+        exec_interactive(
+            container_id,
+            devcontainer,
+            &remote_env,
+            &secrets,
+            &cmd,
+            &workdir,
+            input,
+        )
     }
 }
 
+/// Kill the exec with Docker-reported `pid`, after checking that it is one of the container's
+/// running execs.
+async fn kill(devcontainer: &DevcontainerState, container_id: &str, pid: u32) -> eyre::Result<()> {
+    let sessions = devcontainer.docker.exec_sessions(container_id).await?;
+    let Some(session) = sessions.iter().find(|s| s.pid == pid) else {
+        return Err(eyre!("no running exec with pid {pid}")
+            .suggestion("run `dc show execs` to list running execs"));
+    };
+
+    // Docker reports PIDs from the daemon's namespace; `kill` in the container needs its own.
+    let status = std::fs::read_to_string(format!("/proc/{pid}/status"))
+        .wrap_err_with(|| format!("failed to look up pid {pid}"))
+        .suggestion("killing execs needs the Docker daemon to run on this machine")?;
+    let container_pid = namespaced_pid(&status)
+        .ok_or_else(|| eyre!("could not find the container pid of {pid}"))?;
+    // The exec may have exited since we listed it, and its PID gone to something else.
+    if !devcontainer
+        .docker
+        .exec_running_as(&session.id, pid)
+        .await?
+    {
+        return Err(eyre!("exec with pid {pid} has exited"));
+    }
+
+    let status = tokio::process::Command::new("docker")
+        .args(["exec", "-u", "root", container_id, "kill"])
+        .arg(container_pid.to_string())
+        .status()
+        .await
+        .wrap_err("failed to run `docker exec`")?;
+    if !status.success() {
+        return Err(eyre!("failed to kill pid {pid}: {status}"));
+    }
+    eprintln!("{} killed `{}` ({pid})", "✓".green(), session.command);
+    Ok(())
+}
+
+/// The innermost PID from the `NSpid:` line of `/proc/<pid>/status`.
+fn namespaced_pid(status: &str) -> Option<u32> {
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("NSpid:"))?
+        .split_whitespace()
+        .last()?
+        .parse()
+        .ok()
+}
+
+/// The command to feed a script to: its shebang line if it has one, `sh` otherwise.
+fn script_interpreter(script: &str) -> Vec<String> {
+    script
+        .lines()
+        .next()
+        .and_then(|line| line.strip_prefix("#!"))
+        .map(|line| line.split_whitespace().map(str::to_owned).collect())
+        .filter(|args: &Vec<String>| !args.is_empty())
+        .unwrap_or_else(|| vec!["sh".to_owned()])
+}
+
+/// Run when there's neither a command nor a `defaultExec`: the user's shell from `/etc/passwd`, as
+/// a login shell.
+const LOGIN_SHELL: &[&str] = &[
+    "/bin/sh",
+    "-c",
+    r#"shell=$(awk -F: -v u="$(id -un)" '$1 == u { print $7 }' /etc/passwd); exec "${shell:-/bin/sh}" -l"#,
+];
+
 pub(crate) fn exec_interactive(
     container_id: &str,
     devcontainer: &DevcontainerState,
     remote_env: &IndexMap<String, Option<String>>,
+    secrets: &IndexMap<String, String>,
     cmd_args: &[String],
+    workdir: &Path,
+    input: ExecInput,
 ) -> eyre::Result<()> {
     let mut cmd = std::process::Command::new("docker");
     cmd.arg("exec");
-    if std::io::stdin().is_terminal() {
-        cmd.arg("-it");
+    let detach = matches!(input, ExecInput::Detach);
+    let mut clear = false;
+    match input {
+        ExecInput::Inherit {
+            tty,
+            clear: want_clear,
+        } => {
+            if std::io::stdin().is_terminal() {
+                cmd.arg(if tty { "-it" } else { "-i" });
+                clear = tty && want_clear && std::io::stdout().is_terminal();
+            }
+        }
+        ExecInput::Script(file) => {
+            cmd.arg("-i").stdin(file);
+        }
+        ExecInput::Detach => {
+            cmd.arg("-d");
+        }
     }
 
     let dc_options = devcontainer.devconcurrent();
@@ -80,8 +249,12 @@ pub(crate) fn exec_interactive(
     if let Some(u) = devcontainer.config.remote_user.as_deref() {
         cmd.args(["-u", u]);
     }
-    cmd.arg("-w").arg(&devcontainer.config.workspace_folder);
+    cmd.arg("-w").arg(workdir);
 
+    // Secrets go through our environment rather than the command line.
+    for (k, v) in secrets {
+        cmd.arg("-e").arg(k).env(k, v);
+    }
     for (k, v) in remote_env {
         // null in remoteEnv means "unset" per spec; we can't truly unset PID-1-inherited vars via
         // `docker exec`, so set to empty string — closer to intent than the reference's literal
@@ -92,21 +265,69 @@ pub(crate) fn exec_interactive(
 
     cmd.arg(container_id);
 
-    if cmd_args.is_empty() {
-        cmd.args(
-            dc_options
-                .default_exec
-                .as_ref()
-                .ok_or_else(|| eyre!("no command provided and no default configured"))?
-                .as_args(),
-        );
+    let args = if !cmd_args.is_empty() {
+        cmd_args.iter().map(String::as_str).collect()
+    } else if let Some(default) = &dc_options.default_exec {
+        default.as_args()
+    } else if detach {
+        return Err(eyre!("no command provided and no default configured"))
+            .suggestion("a login shell is only started for interactive execs");
     } else {
-        cmd.args(cmd_args);
+        LOGIN_SHELL.to_vec()
+    };
+    cmd.args(&args);
+
+    if detach {
+        let status = cmd.status().wrap_err("failed to run `docker exec`")?;
+        if !status.success() {
+            return Err(eyre!("`docker exec -d` failed: {status}"));
+        }
+        eprintln!(
+            "{} started `{}` in the background; see `dc status` for running execs",
+            "✓".green(),
+            args.join(" ")
+        );
+        return Ok(());
     }
 
     // Restore cursor visibility — indicatif hides it for spinners and exec()
     // replaces the process before indicatif's cleanup can run.
     let _ = crossterm::execute!(std::io::stderr(), crossterm::cursor::Show);
+    if clear {
+        let _ = crossterm::execute!(
+            std::io::stdout(),
+            crossterm::terminal::Clear(crossterm::terminal::ClearType::All),
+            crossterm::cursor::MoveTo(0, 0),
+        );
+    }
 
     Err(cmd.exec().into())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn script_interpreter_uses_shebang() {
+        assert_eq!(
+            script_interpreter("#!/usr/bin/env python3\nprint('hi')\n"),
+            ["/usr/bin/env", "python3"]
+        );
+        assert_eq!(script_interpreter("#!/bin/bash -e\n"), ["/bin/bash", "-e"]);
+    }
+
+    #[test]
+    fn namespaced_pid_is_innermost() {
+        let status = "Name:\tsleep\nPid:\t4242\nNSpid:\t4242\t17\nPPid:\t1\n";
+        assert_eq!(namespaced_pid(status), Some(17));
+        assert_eq!(namespaced_pid("Name:\tsleep\n"), None);
+    }
+
+    #[test]
+    fn script_interpreter_defaults_to_sh() {
+        assert_eq!(script_interpreter("echo hi\n"), ["sh"]);
+        assert_eq!(script_interpreter("#!\n"), ["sh"]);
+        assert_eq!(script_interpreter(""), ["sh"]);
+    }
+}
@@ -0,0 +1,239 @@
+use std::fmt::Write as _;
+use std::path::PathBuf;
+
+use clap::Args;
+use clap_complete::ArgValueCompleter;
+use eyre::{WrapErr, eyre};
+
+use crate::cli::State;
+use crate::complete::complete_workspace;
+use crate::devcontainer::lifecycle_command::LifecycleCommand;
+use crate::devcontainer::substitution;
+use crate::docker::compose::{compose_cmd, override_path};
+
+/// Write a workspace's compose config and lifecycle commands to a directory, to run it without dc
+#[derive(Debug, Args)]
+pub(crate) struct Export {
+    /// Workspace name [default: current working directory]
+    #[arg(add = ArgValueCompleter::new(complete_workspace))]
+    workspace: Option<String>,
+
+    /// Directory to write to [default: `<workspace>-export`]
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+/// What the generated script and README need to know about the workspace.
+struct Bundle<'a> {
+    compose_project: String,
+    service: &'a str,
+    /// `docker exec` options: user, working directory, and environment.
+    exec_args: Vec<String>,
+    secrets: Vec<&'a str>,
+    initialize: Option<&'a LifecycleCommand>,
+    /// In-container lifecycle commands, in the order `dc up` runs them.
+    lifecycle: Vec<(&'static str, &'a LifecycleCommand)>,
+}
+
+impl Export {
+    pub(crate) async fn run(self, state: State<'_>) -> eyre::Result<()> {
+        let workspace = state.resolve_workspace(self.workspace).await?;
+        let devcontainer = state.devcontainer_for(&workspace.path)?;
+        let config = &devcontainer.config;
+        let output = self
+            .output
+            .unwrap_or_else(|| PathBuf::from(format!("{}-export", workspace.name)));
+        std::fs::create_dir_all(&output)
+            .wrap_err_with(|| format!("failed to create {}", output.display()))?;
+
+        let mut config_cmd = compose_cmd(&devcontainer, &workspace)?;
+        config_cmd.arg("config");
+        let out = config_cmd.output().await?;
+        if !out.status.success() {
+            return Err(eyre!(
+                "docker compose config failed: {}",
+                String::from_utf8_lossy(&out.stderr).trim()
+            ));
+        }
+        std::fs::write(output.join("compose.yml"), &out.stdout)?;
+        std::fs::copy(override_path(&workspace)?, output.join("override.yml"))?;
+
+        // Without a container, `${containerEnv:...}` in `remoteEnv` renders empty.
+        let context = substitution::Context::new(&workspace.path, &config.workspace_folder);
+        let mut exec_args = Vec::new();
+        if let Some(user) = &config.remote_user {
+            exec_args.extend(["-u".to_owned(), user.clone()]);
+        }
+        exec_args.extend([
+            "-w".to_owned(),
+            config.workspace_folder.display().to_string(),
+        ]);
+        for key in config.secrets.keys() {
+            exec_args.extend(["-e".to_owned(), key.clone()]);
+        }
+        for (key, template) in &config.remote_env {
+            let value = template.as_ref().map(|t| t.render(&context));
+            exec_args.extend([
+                "-e".to_owned(),
+                format!("{key}={}", value.unwrap_or_default()),
+            ]);
+        }
+
+        let lifecycle = [
+            ("onCreateCommand", &config.on_create_command),
+            ("updateContentCommand", &config.update_content_command),
+            ("postCreateCommand", &config.post_create_command),
+            ("postStartCommand", &config.post_start_command),
+        ]
+        .into_iter()
+        .filter_map(|(name, cmd)| Some((name, cmd.as_ref()?)))
+        .collect();
+
+        let bundle = Bundle {
+            compose_project: workspace.compose_project_name(),
+            service: &config.service,
+            exec_args,
+            secrets: config.secrets.keys().map(String::as_str).collect(),
+            initialize: config.initialize_command.as_ref(),
+            lifecycle,
+        };
+
+        let script_path = output.join("up.sh");
+        std::fs::write(&script_path, bundle.script()?)?;
+        std::fs::set_permissions(
+            &script_path,
+            std::os::unix::fs::PermissionsExt::from_mode(0o755),
+        )?;
+        std::fs::write(output.join("README.md"), bundle.readme(&workspace.name)?)?;
+
+        eprintln!("Exported {} to {}", workspace.name, output.display());
+        Ok(())
+    }
+}
+
+impl Bundle<'_> {
+    fn compose(&self) -> eyre::Result<String> {
+        join([
+            "docker",
+            "compose",
+            "-p",
+            &self.compose_project,
+            "-f",
+            "compose.yml",
+        ])
+    }
+
+    /// A script that brings the environment up and runs its lifecycle commands, as `dc up` would
+    /// for a new workspace.
+    fn script(&self) -> eyre::Result<String> {
+        let mut s = String::from("#!/bin/sh\nset -eu\ncd \"$(dirname \"$0\")\"\n\n");
+        if let Some(cmd) = self.initialize {
+            s.push_str("# initializeCommand, on the host\n");
+            write_commands(&mut s, cmd, "")?;
+            s.push('\n');
+        }
+
+        let compose = self.compose()?;
+        writeln!(s, "{compose} up -d --build --remove-orphans")?;
+        writeln!(
+            s,
+            "container=$({compose} ps -q {})",
+            shlex::try_quote(self.service)?
+        )?;
+        let exec = join(
+            ["docker", "exec"]
+                .into_iter()
+                .chain(self.exec_args.iter().map(String::as_str)),
+        )?;
+        writeln!(s, "run() {{ {exec} \"$container\" \"$@\"; }}")?;
+
+        for (name, cmd) in &self.lifecycle {
+            writeln!(s, "\n# {name}")?;
+            write_commands(&mut s, cmd, "run ")?;
+        }
+        Ok(s)
+    }
+
+    fn readme(&self, workspace: &str) -> eyre::Result<String> {
+        let compose = self.compose()?;
+        let exec = join(
+            ["exec"]
+                .into_iter()
+                .chain(self.exec_args.iter().map(String::as_str))
+                .chain([self.service]),
+        )?;
+        let mut s = format!(
+            "# {workspace}\n\n\
+             Exported by devconcurrent.\n\n\
+             * `compose.yml`: the resolved compose config, including the override.\n\
+             * `override.yml`: the override devconcurrent generated, for reference.\n\
+             * `up.sh`: brings the environment up and runs its lifecycle commands.\n\n\
+             Paths in `compose.yml` are absolute paths on the machine it was exported \
+             from; adjust bind mounts before running it elsewhere.\n\n\
+             To get a shell in the `{service}` service:\n\n\
+             ```sh\n{compose} {exec} sh\n```\n",
+            service = self.service,
+        );
+        if !self.secrets.is_empty() {
+            s.push_str("\nSet these secrets in your environment first:\n\n");
+            for secret in &self.secrets {
+                writeln!(s, "* `{secret}`")?;
+            }
+        }
+        Ok(s)
+    }
+}
+
+/// Write `cmd` as shell lines, each prefixed with `prefix`; parallel commands run as background
+/// jobs that are then waited on.
+fn write_commands(s: &mut String, cmd: &LifecycleCommand, prefix: &str) -> eyre::Result<()> {
+    match cmd {
+        LifecycleCommand::Single(cmd) => writeln!(s, "{prefix}{}", join(cmd.as_args())?)?,
+        LifecycleCommand::Parallel(cmds) => {
+            let mut pids = Vec::new();
+            for (i, cmd) in cmds.values().enumerate() {
+                writeln!(s, "{prefix}{} &", join(cmd.as_args())?)?;
+                writeln!(s, "pid{i}=$!")?;
+                pids.push(format!("wait \"$pid{i}\""));
+            }
+            writeln!(s, "{}", pids.join("; "))?;
+        }
+    }
+    Ok(())
+}
+
+fn join<'a>(args: impl IntoIterator<Item = &'a str>) -> eyre::Result<String> {
+    Ok(shlex::try_join(args)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn script_runs_lifecycle_commands_in_the_container() {
+        let post_create: LifecycleCommand =
+            serde_json::from_str(r#""npm install && npm run build""#).unwrap();
+        let post_start: LifecycleCommand =
+            serde_json::from_str(r#"{"db": ["migrate", "up"], "watch": "npm run watch"}"#).unwrap();
+        let bundle = Bundle {
+            compose_project: "feature_devcontainer".into(),
+            service: "app",
+            exec_args: vec!["-u".into(), "node".into(), "-e".into(), "TOKEN".into()],
+            secrets: vec!["TOKEN"],
+            initialize: None,
+            lifecycle: vec![
+                ("postCreateCommand", &post_create),
+                ("postStartCommand", &post_start),
+            ],
+        };
+        let script = bundle.script().unwrap();
+        assert!(script.contains(
+            "container=$(docker compose -p feature_devcontainer -f compose.yml ps -q app)\n"
+        ));
+        assert!(script.contains("run() { docker exec -u node -e TOKEN \"$container\" \"$@\"; }\n"));
+        assert!(script.contains("run /bin/sh -c 'npm install && npm run build'\n"));
+        assert!(script.contains("run migrate up &\npid0=$!\n"));
+        assert!(script.contains("wait \"$pid0\"; wait \"$pid1\"\n"));
+    }
+}
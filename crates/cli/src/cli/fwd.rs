@@ -1,21 +1,25 @@
+use std::collections::{HashMap, HashSet};
 use std::net::{IpAddr, Ipv4Addr};
 
 use clap::{Args, Subcommand};
 use clap_complete::ArgValueCompleter;
-use docker::{FORWARD_LABEL, FORWARD_TARGET_LABEL, PROJECT_LABEL};
-use eyre::eyre;
+use docker::{FORWARD_LABEL, FORWARD_TARGET_LABEL, MANAGED_LABEL, PROJECT_LABEL, WORKSPACE_LABEL};
+use eyre::{bail, eyre};
 
 use color_eyre::owo_colors::OwoColorize;
 
 use crate::cli::State;
 use crate::complete::complete_workspace;
-use crate::config::Config;
 use crate::devcontainer::forward_port::ForwardPort;
 use crate::state::DevcontainerState;
+use crate::subscriber::{emit_json, json_progress};
 use crate::workspace::Workspace;
 
 const SOCAT_IMAGE: &str = "docker.io/alpine/socat:latest";
 
+/// How many times `--auto-port` asks the OS for a port before giving up.
+const ALLOCATE_ATTEMPTS: usize = 8;
+
 /// Forward configured `forwardPorts` to a running workspace
 #[derive(Debug, Args)]
 pub(crate) struct Fwd {
@@ -23,6 +27,14 @@ pub(crate) struct Fwd {
     #[arg(short, long, add = ArgValueCompleter::new(complete_workspace))]
     workspace: Option<String>,
 
+    /// Move ports that are already taken to a free host port picked by the OS
+    #[arg(long)]
+    auto_port: bool,
+
+    /// Forward to this compose service instead of the primary container
+    #[arg(long)]
+    service: Option<String>,
+
     #[command(subcommand)]
     command: Option<FwdCommands>,
 }
@@ -31,64 +43,94 @@ pub(crate) struct Fwd {
 enum FwdCommands {
     /// Stop forwarding ports (remove sidecar containers)
     Stop,
+    /// Remove forwards whose container is gone, forwarding again if the workspace is back up
+    Reconcile,
 }
 
 impl Fwd {
-    pub(crate) async fn run(self, project: Option<String>) -> eyre::Result<()> {
-        let config = Config::load()?;
-        let state = State::new(project, &config).await?;
+    pub(crate) async fn run(self, state: State<'_>) -> eyre::Result<()> {
         match self.command {
             Some(FwdCommands::Stop) => {
                 let devcontainer = state.try_devcontainer()?;
-                remove_sidecars(&state, &devcontainer.docker.client).await
+                remove_sidecars(&state, devcontainer.docker.client().await?, None).await
             }
+            Some(FwdCommands::Reconcile) => reconcile(&state).await,
             None => {
                 let workspace = state.resolve_workspace(self.workspace).await?;
                 let devcontainer = state.devcontainer_for(&workspace.path)?;
-                forward(&devcontainer, &workspace).await
+                forward(
+                    &devcontainer,
+                    &workspace,
+                    self.auto_port,
+                    self.service.as_deref(),
+                )
+                .await
             }
         }
     }
 }
 
+/// Where a configured port ended up on the host.
+#[derive(Debug, Clone)]
+struct Mapping {
+    port: ForwardPort,
+    host_port: u16,
+}
+
 pub(crate) async fn forward(
     devcontainer: &DevcontainerState,
     workspace: &Workspace<'_>,
+    auto_port: bool,
+    service: Option<&str>,
 ) -> eyre::Result<()> {
-    remove_sidecars(workspace.state, &devcontainer.docker.client).await?;
+    let client = devcontainer.docker.client().await?;
+    remove_sidecars(workspace.state, client, None).await?;
 
     let ws = workspace.devcontainer(devcontainer).await?;
     let cid = ws.service_container_id()?;
-    let ports = &devcontainer.config.forward_ports;
+    let mut ports = devcontainer.config.forward_ports.clone();
 
     if ports.is_empty() {
         return Ok(());
     }
 
-    let free: Vec<bool> = ports.iter().map(|p| port_is_free(p.port)).collect();
-    let available: Vec<ForwardPort> = ports
-        .iter()
-        .zip(&free)
-        .filter(|(_, ok)| **ok)
-        .map(|(p, _)| p.clone())
-        .collect();
+    if let Some(service) = service {
+        let services = devcontainer.docker.workspace_compose_ips(workspace).await?;
+        if !services.iter().any(|(s, _)| s == service) {
+            let known: Vec<&str> = services.iter().map(|(s, _)| s.as_str()).collect();
+            bail!(
+                "no service {service} in workspace {}; services: {}",
+                workspace.name,
+                known.join(", ")
+            );
+        }
+        for port in &mut ports {
+            port.service = Some(service.to_owned());
+        }
+    }
+
+    // Forwards from other projects hold their host ports through docker, so
+    // name them in the report rather than just saying "in use".
+    let owners = devcontainer.docker.forward_owners().await?;
+    let plan = plan_ports(&ports, auto_port, &owners, port_is_free, free_port);
+    let available: Vec<Mapping> = plan.iter().filter_map(|(m, _)| m.clone()).collect();
 
     if !available.is_empty() {
         // Get container's network name for the outer sidecar
-        let network_name = container_network(&devcontainer.docker.client, cid).await?;
+        let network_name = container_network(client, cid).await?;
 
-        devcontainer.docker.client.ensure_image(SOCAT_IMAGE).await?;
+        client.ensure_image(SOCAT_IMAGE).await?;
 
         let volume_name = format!("devconcurrent-fwd-{}", workspace.compose_project_name());
 
-        let mut create = devcontainer.docker.client.create_volume(&volume_name);
+        let mut create = client.create_volume(&volume_name);
         for (key, value) in workspace.docker_fwd_labels() {
             create = create.with_label(key.to_owned(), value.to_owned());
         }
         create.call().await?;
 
         create_inner_sidecar(
-            &devcontainer.docker.client,
+            client,
             workspace,
             &workspace.compose_project_name(),
             cid,
@@ -97,7 +139,7 @@ pub(crate) async fn forward(
         )
         .await?;
         create_outer_sidecar(
-            &devcontainer.docker.client,
+            client,
             workspace,
             &workspace.compose_project_name(),
             cid,
@@ -108,17 +150,91 @@ pub(crate) async fn forward(
         .await?;
     }
 
-    for (port, &ok) in ports.iter().zip(&free) {
-        if ok {
-            eprintln!("{} {port}", "✓".green());
-        } else {
-            eprintln!("{} {port} (already in use)", "✗".red());
+    for (mapping, conflict) in &plan {
+        if json_progress() {
+            emit_json(serde_json::json!({
+                "event": "forward",
+                "workspace": workspace.name,
+                "service": mapping.as_ref().and_then(|m| m.port.service.as_deref()),
+                "port": mapping.as_ref().map(|m| m.port.port),
+                "host_port": mapping.as_ref().map(|m| m.host_port),
+                "note": conflict,
+            }));
+            continue;
+        }
+        match (mapping, conflict) {
+            (Some(m), None) => eprintln!("{} {}", "✓".green(), m.port),
+            (Some(m), Some(reason)) => eprintln!(
+                "{} {} -> localhost:{} ({reason})",
+                "✓".green(),
+                m.port,
+                m.host_port
+            ),
+            (None, Some(reason)) => eprintln!("{} {reason}", "✗".red()),
+            (None, None) => unreachable!("skipped ports always carry a reason"),
         }
     }
 
     Ok(())
 }
 
+/// Decide a host port for each configured port. Ports that are taken are
+/// moved to an OS-assigned free port when `auto_port` is set, and skipped otherwise;
+/// either way the second element says why.
+fn plan_ports(
+    ports: &[ForwardPort],
+    auto_port: bool,
+    owners: &HashMap<u16, String>,
+    is_free: impl Fn(u16) -> bool,
+    mut allocate: impl FnMut() -> Option<u16>,
+) -> Vec<(Option<Mapping>, Option<String>)> {
+    // Never move a port onto one that is configured, even if it comes later.
+    let configured: HashSet<u16> = ports.iter().map(|p| p.port).collect();
+    let mut claimed = HashSet::new();
+    let usable = |p: u16, claimed: &HashSet<u16>| {
+        !claimed.contains(&p) && !owners.contains_key(&p) && is_free(p)
+    };
+
+    ports
+        .iter()
+        .map(|port| {
+            let preferred = port.port;
+            if usable(preferred, &claimed) {
+                claimed.insert(preferred);
+                let mapping = Mapping {
+                    port: port.clone(),
+                    host_port: preferred,
+                };
+                return (Some(mapping), None);
+            }
+
+            let why = match owners.get(&preferred) {
+                Some(owner) => format!("{preferred} is forwarded by {owner}"),
+                None => format!("{preferred} is already in use"),
+            };
+            if !auto_port {
+                return (None, Some(format!("{port} ({why})")));
+            }
+            // The OS may hand back a port we've already settled on, since
+            // nothing stays bound; ask again a few times before giving up.
+            let host_port = (0..ALLOCATE_ATTEMPTS)
+                .filter_map(|_| allocate())
+                .find(|&p| !configured.contains(&p) && usable(p, &claimed));
+            match host_port {
+                Some(host_port) => {
+                    claimed.insert(host_port);
+                    let mapping = Mapping {
+                        port: port.clone(),
+                        host_port,
+                    };
+                    (Some(mapping), Some(why))
+                }
+                None => (None, Some(format!("{port} ({why}, no free port found)"))),
+            }
+        })
+        .collect()
+}
+
 async fn container_network(client: &docker::Docker, cid: &str) -> eyre::Result<String> {
     let details = client.inspect_container(cid).await?;
     details
@@ -137,13 +253,13 @@ async fn create_inner_sidecar(
     compose_project_name: &str,
     cid: &str,
     volume_name: &str,
-    ports: &[ForwardPort],
+    ports: &[Mapping],
 ) -> eyre::Result<()> {
     let name = format!("devconcurrent-fwd-inner-{compose_project_name}");
 
     let socat_cmds: Vec<String> = ports
         .iter()
-        .map(|p| {
+        .map(|Mapping { port: p, .. }| {
             let target = p.service.as_deref().unwrap_or("127.0.0.1");
             format!(
                 "socat UNIX-LISTEN:/socks/{}.sock,fork,reuseaddr TCP:{target}:{}",
@@ -179,13 +295,13 @@ async fn create_outer_sidecar(
     cid: &str,
     network_name: &str,
     volume_name: &str,
-    ports: &[ForwardPort],
+    ports: &[Mapping],
 ) -> eyre::Result<()> {
     let name = format!("devconcurrent-fwd-{compose_project_name}");
 
     let socat_cmds: Vec<String> = ports
         .iter()
-        .map(|p| {
+        .map(|Mapping { port: p, .. }| {
             format!(
                 "socat TCP-LISTEN:{},fork,reuseaddr UNIX:/socks/{}.sock",
                 p.port, p.port
@@ -206,8 +322,8 @@ async fn create_outer_sidecar(
     for (key, value) in workspace.docker_fwd_labels() {
         create = create.with_label(key, value);
     }
-    for p in ports {
-        create = create.with_tcp_port_binding(p.port, loopback, p.port);
+    for m in ports {
+        create = create.with_tcp_port_binding(m.port.port, loopback, m.host_port);
     }
     let id = create.call().await?;
     client.start_container(&id).await?;
@@ -221,20 +337,64 @@ fn join_background(cmds: &[String]) -> String {
     parts.join(" ")
 }
 
+/// Clear out sidecars left forwarding to a dead container, then forward again to the workspace's
+/// current container if it's running.
+async fn reconcile(state: &State<'_>) -> eyre::Result<()> {
+    let devcontainer = state.try_devcontainer()?;
+    let stale = devcontainer
+        .docker
+        .stale_forwards(&state.project_name)
+        .await?;
+    if stale.is_empty() {
+        eprintln!("{} forwards are healthy", "✓".green());
+        return Ok(());
+    }
+
+    remove_sidecars(state, devcontainer.docker.client().await?, Some(&stale)).await?;
+    for name in stale {
+        eprintln!("{} removed stale forwards of {name}", "✓".green());
+        // The label holds the exact name; don't let it match some other workspace.
+        let workspace = state.exact_workspace(name).await?;
+        if !workspace.path.exists() {
+            tracing::warn!(
+                "workspace {} no longer exists; not forwarding",
+                workspace.name
+            );
+            continue;
+        }
+        let devcontainer = state.devcontainer_for(&workspace.path)?;
+        let status = workspace.devcontainer(&devcontainer).await?.status();
+        if status == Some(docker::ContainerStatus::Running) {
+            forward(&devcontainer, &workspace, false, None).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Remove the project's forward sidecars and their volumes: all of them, or only those of
+/// `workspaces`.
 pub(crate) async fn remove_sidecars(
     state: &State<'_>,
     client: &docker::Docker,
+    workspaces: Option<&[String]>,
 ) -> eyre::Result<()> {
     let project = state.project_name.as_str();
+    let selected = |workspace: Option<&String>| {
+        workspaces.is_none_or(|workspaces| workspace.is_some_and(|ws| workspaces.contains(ws)))
+    };
 
     let sidecars = client
         .list_containers()
         .all(true)
+        .with_label(MANAGED_LABEL, state.config.managed_label_value())
         .with_label(FORWARD_LABEL, "true")
         .with_label(PROJECT_LABEL, project)
         .call()
         .await?;
-    for c in sidecars {
+    for c in sidecars
+        .into_iter()
+        .filter(|c| selected(c.labels.get(WORKSPACE_LABEL)))
+    {
         match client.remove_container(&c.id).force(true).call().await {
             Ok(()) | Err(docker::Error::NotFound) => {}
             Err(e) => tracing::warn!(container = %c.id, "failed to remove sidecar: {e}"),
@@ -243,11 +403,15 @@ pub(crate) async fn remove_sidecars(
 
     let volumes = client
         .list_volumes()
+        .with_label(MANAGED_LABEL, state.config.managed_label_value())
         .with_label(FORWARD_LABEL, "true")
         .with_label(PROJECT_LABEL, project)
         .call()
         .await?;
-    for vol in volumes {
+    for vol in volumes
+        .into_iter()
+        .filter(|v| selected(v.labels.get(WORKSPACE_LABEL)))
+    {
         match client.remove_volume(&vol.name).call().await {
             Ok(()) | Err(docker::Error::NotFound) => {}
             Err(e) => tracing::warn!(volume = %vol.name, "failed to remove volume: {e}"),
@@ -260,3 +424,57 @@ pub(crate) async fn remove_sidecars(
 fn port_is_free(port: u16) -> bool {
     std::net::TcpListener::bind(("127.0.0.1", port)).is_ok()
 }
+
+/// Ask the OS for a currently free loopback port.
+fn free_port() -> Option<u16> {
+    let listener = std::net::TcpListener::bind(("127.0.0.1", 0)).ok()?;
+    Some(listener.local_addr().ok()?.port())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn port(port: u16) -> ForwardPort {
+        ForwardPort {
+            service: None,
+            port,
+        }
+    }
+
+    fn host_ports(plan: &[(Option<Mapping>, Option<String>)]) -> Vec<Option<u16>> {
+        plan.iter()
+            .map(|(m, _)| m.as_ref().map(|m| m.host_port))
+            .collect()
+    }
+
+    #[test]
+    fn plan_skips_taken_ports_without_auto_port() {
+        let owners = HashMap::from([(3000, "other/main".to_string())]);
+        let plan = plan_ports(&[port(3000), port(5432)], false, &owners, |_| true, || None);
+        assert_eq!(host_ports(&plan), [None, Some(5432)]);
+        assert_eq!(
+            plan[0].1.as_deref(),
+            Some("3000 (3000 is forwarded by other/main)")
+        );
+    }
+
+    #[test]
+    fn plan_moves_taken_ports_with_auto_port() {
+        let owners = HashMap::from([(3000, "other/main".to_string())]);
+        // The "OS" first offers a configured port, then one already picked.
+        let mut offers = [3001, 40000, 40000, 40001].into_iter();
+        let plan = plan_ports(
+            &[port(3000), port(3001), port(3002)],
+            true,
+            &owners,
+            |p| p != 3002,
+            || offers.next(),
+        );
+        assert_eq!(host_ports(&plan), [Some(40000), Some(3001), Some(40001)]);
+        assert_eq!(
+            plan[0].1.as_deref(),
+            Some("3000 is forwarded by other/main")
+        );
+    }
+}
@@ -3,9 +3,8 @@ use std::path::Path;
 use clap::Args;
 use clap_complete::engine::ArgValueCompleter;
 
-use crate::cli::State;
+use crate::cli::{State, up::Up};
 use crate::complete::complete_workspace;
-use crate::config::Config;
 use crate::helpers::forward_to_shell;
 
 /// Cd into the workspace directory (only if using via shell wrapper).
@@ -14,19 +13,81 @@ pub(crate) struct Go {
     /// Workspace name
     #[arg(add = ArgValueCompleter::new(complete_workspace))]
     workspace: String,
+
+    /// Bring the workspace up first if it doesn't exist
+    #[arg(short, long)]
+    create: bool,
 }
 
 impl Go {
-    pub(crate) async fn run(self, project: Option<String>) -> eyre::Result<()> {
-        let config = Config::load()?;
-        let state = State::new(project, &config).await?;
-        let ws = state.resolve_workspace(Some(self.workspace)).await?;
-        go(&ws.path)
+    pub(crate) async fn run(self, state: State<'_>) -> eyre::Result<()> {
+        let ws = state
+            .resolve_workspace(Some(self.workspace.clone()))
+            .await?;
+        if ws.path.exists() {
+            return go(&ws.path);
+        }
+
+        if !self.create {
+            eyre::bail!(
+                "workspace '{0}' does not exist; create it with `dc up {0}` or `dc go --create {0}`",
+                ws.name
+            );
+        }
+        Up::create_and_go(self.workspace).run(state).await
     }
 }
 
 pub(crate) fn go(path: &Path) -> eyre::Result<()> {
+    forward_to_shell(&cd_command(path)?)
+}
+
+/// A `cd` that survives the wrapper's `eval`, whatever the path contains.
+fn cd_command(path: &Path) -> eyre::Result<String> {
     let path_str = path.to_string_lossy();
     let quoted = shlex::try_quote(&path_str)?;
-    forward_to_shell(&format!("cd {quoted}"))
+    Ok(format!("cd {quoted}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+
+    use clap_complete::Shell;
+
+    use super::*;
+    use crate::complete::shell_function;
+
+    #[test]
+    fn bash_wrapper_cds_into_awkward_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("a b $HOME 'q' \"dq\" -x");
+        std::fs::create_dir(&target).unwrap();
+
+        // Stand in for the binary: emit the `cd` on the wrapper's fd, as `go` would.
+        let cd = cd_command(&target).unwrap();
+        let fake = dir.path().join("fake dc");
+        let script = format!(
+            "#!/bin/sh\nprintf '%s\\n' {} >&3\n",
+            shlex::try_quote(&cd).unwrap()
+        );
+        std::fs::write(&fake, script).unwrap();
+        std::fs::set_permissions(&fake, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let wrapper = shell_function(Shell::Bash, &fake.to_string_lossy()).unwrap();
+        let out = std::process::Command::new("bash")
+            .arg("-c")
+            .arg(format!("{wrapper}\ndc go && pwd"))
+            .output()
+            .unwrap();
+        assert!(
+            out.status.success(),
+            "{}",
+            String::from_utf8_lossy(&out.stderr)
+        );
+        assert_eq!(
+            String::from_utf8(out.stdout).unwrap().trim_end(),
+            target.to_string_lossy()
+        );
+    }
 }
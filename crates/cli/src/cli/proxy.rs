@@ -1,4 +1,5 @@
 use std::collections::BTreeMap;
+use std::path::Path;
 use std::sync::LazyLock;
 use std::time::Duration;
 
@@ -57,14 +58,14 @@ struct ProxyArgs {
 impl Proxy {
     /// This command is a bit different than most; it needs to operate on multiple projects, but we
     /// still set a workspace so that a user can edit proxy settings from a workspace and test them.
-    pub(crate) async fn run(self, project: Option<String>) -> Result<()> {
+    pub(crate) async fn run(self, project: Option<String>, config: Option<&Path>) -> Result<()> {
         match self.command {
             ProxyCommands::Up(args) => {
-                let proxy = ProxyState::resolve(project, args.workspace).await?;
+                let proxy = ProxyState::resolve(project, args.workspace, config).await?;
                 proxy_up(&proxy).await
             }
             ProxyCommands::Status(args) => {
-                let proxy = ProxyState::resolve(project, args.workspace).await?;
+                let proxy = ProxyState::resolve(project, args.workspace, config).await?;
                 proxy_status(&proxy).await
             }
             ProxyCommands::Down => proxy_down().await,
@@ -26,8 +26,9 @@ impl ProxyState {
     pub(crate) async fn resolve(
         project: Option<String>,
         workspace: Option<String>,
+        config_path: Option<&Path>,
     ) -> Result<Self> {
-        let config = Config::load()?;
+        let config = Config::load(config_path)?;
         let state = State::new(project, &config).await?;
         let workspace = state.resolve_workspace(workspace).await.ok();
         Self::from_workspace(&config, workspace.as_ref()).await
@@ -39,7 +40,7 @@ impl ProxyState {
     ) -> Result<Self> {
         // Reuse the docker connection the workspace already opened, if any.
         let docker = match workspace.and_then(|w| w.state.devcontainer.as_ref()) {
-            Some(dc) => dc.docker.client.clone(),
+            Some(dc) => dc.docker.client().await?.clone(),
             None => Docker::connect().await.wrap_err("connect to docker")?,
         };
 
@@ -95,7 +96,7 @@ impl ProxyState {
 }
 
 fn load_proxy_options(project: &Project, workspace_dir: &Path) -> Result<Option<ProxyOptions>> {
-    let dc_path = DevcontainerConfig::find_config(workspace_dir);
+    let dc_path = project.find_devcontainer(workspace_dir)?;
     let Some(dc_config) = DevcontainerConfig::load(dc_path.as_deref(), project)? else {
         return Ok(None);
     };
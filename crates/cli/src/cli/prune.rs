@@ -0,0 +1,42 @@
+use clap::Args;
+
+use crate::cli::State;
+use crate::docker::compose::remove_override_file;
+use crate::workspace::Workspace;
+use crate::worktree;
+
+/// Forget workspaces whose worktrees were deleted without `dc destroy`
+#[derive(Debug, Args)]
+pub(crate) struct Prune {
+    /// Only list the broken workspaces
+    #[arg(short = 'n', long)]
+    dry_run: bool,
+}
+
+impl Prune {
+    pub(crate) async fn run(self, state: State<'_>) -> eyre::Result<()> {
+        let mut broken = Workspace::list(&state).await?;
+        broken.retain(|ws| !ws.is_root && worktree::is_broken(&ws.path));
+        if broken.is_empty() {
+            eprintln!("No broken workspaces.");
+            return Ok(());
+        }
+        for ws in &broken {
+            eprintln!("  {} ({})", ws.name, ws.path.display());
+        }
+        if self.dry_run {
+            return Ok(());
+        }
+
+        let paths: Vec<_> = broken.iter().map(|ws| ws.path.clone()).collect();
+        worktree::prune(&state.project.path, &paths).await?;
+        for ws in &broken {
+            remove_override_file(ws);
+        }
+        eprintln!(
+            "Pruned {} workspace(s); their containers, if any, are left as they are",
+            broken.len()
+        );
+        Ok(())
+    }
+}
@@ -1,10 +1,12 @@
+use std::borrow::Cow;
+use std::io::IsTerminal;
+
 use clap::{Args, Subcommand};
 use itertools::Itertools;
+use serde::Serialize;
 
-use crate::{
-    cli::{State, fwd},
-    config::Config,
-};
+use crate::cli::{State, fwd};
+use crate::devcontainer::{PortAttributes, Protocol};
 
 /// Show some value
 #[derive(Debug, Args)]
@@ -21,14 +23,28 @@ enum ShowCommands {
     Workspace(ShowWorkspace),
     /// Show container IP addresses for this workspace
     Ip(Ip),
+    /// Show running `docker exec` processes in this workspace's container
+    Execs(Execs),
+    /// Print the merged devcontainer config as JSON, including inferred defaults
+    Config(ShowConfig),
 }
 
 #[derive(Debug, Args)]
-struct Ports;
+struct Ports {
+    /// Print as JSON, including each port's `portsAttributes`
+    #[arg(long)]
+    json: bool,
+}
 
 #[derive(Debug, Args)]
 struct ShowWorkspace;
 
+#[derive(Debug, Args)]
+struct Execs;
+
+#[derive(Debug, Args)]
+struct ShowConfig;
+
 #[derive(Debug, Args)]
 struct Ip {
     /// Compose service name; if omitted, list all services for this workspace
@@ -36,27 +52,60 @@ struct Ip {
 }
 
 impl Show {
-    pub(crate) async fn run(self, project: Option<String>) -> eyre::Result<()> {
-        let config = Config::load()?;
-        let state = State::new(project, &config).await?;
+    pub(crate) fn needs_docker(&self) -> bool {
+        !matches!(
+            self.command,
+            ShowCommands::Workspace(_) | ShowCommands::Config(_)
+        )
+    }
+
+    pub(crate) async fn run(self, state: State<'_>) -> eyre::Result<()> {
         match self.command {
             ShowCommands::Ports(ports) => ports.run(state).await,
             ShowCommands::Workspace(ws) => ws.run(state).await,
             ShowCommands::Ip(ip) => ip.run(state).await,
+            ShowCommands::Execs(execs) => execs.run(state).await,
+            ShowCommands::Config(config) => config.run(state),
         }
     }
 }
 
 impl Ports {
     async fn run(self, state: State<'_>) -> eyre::Result<()> {
-        let ports = get_ports(state).await?;
+        let ports = get_ports(&state).await?;
+        let config = &state.try_devcontainer()?.config;
 
-        println!("{ports}");
+        if self.json {
+            let ports: Vec<_> = ports
+                .into_iter()
+                .map(|port| PortJson {
+                    port,
+                    attributes: config.port_attributes(port),
+                })
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&ports)?);
+            return Ok(());
+        }
+        for port in ports {
+            let attrs = config.port_attributes(port);
+            let protocol = match attrs.protocol {
+                Protocol::Http => "http",
+                Protocol::Https => "https",
+            };
+            println!("{port} ({}, {protocol})", attrs.label);
+        }
         Ok(())
     }
 }
 
-async fn get_ports(state: State<'_>) -> eyre::Result<String> {
+#[derive(Serialize)]
+struct PortJson {
+    port: u16,
+    #[serde(flatten)]
+    attributes: PortAttributes,
+}
+
+async fn get_ports(state: &State<'_>) -> eyre::Result<Vec<u16>> {
     let workspace = state.resolve_workspace(None).await?;
     let devcontainer = state.try_devcontainer()?;
     let (ports, healthy) = tokio::join!(
@@ -66,10 +115,10 @@ async fn get_ports(state: State<'_>) -> eyre::Result<String> {
     let ports = ports?;
 
     if !ports.is_empty() && !healthy? {
-        fwd::remove_sidecars(&state, &devcontainer.docker.client).await?;
-        Ok(String::new())
+        fwd::remove_sidecars(state, devcontainer.docker.client().await?, None).await?;
+        Ok(Vec::new())
     } else {
-        Ok(ports.into_iter().join(","))
+        Ok(ports)
     }
 }
 
@@ -110,3 +159,83 @@ impl Ip {
         Ok(())
     }
 }
+
+impl Execs {
+    async fn run(self, state: State<'_>) -> eyre::Result<()> {
+        let devcontainer = state.try_devcontainer()?;
+        let workspace = state.resolve_workspace(None).await?;
+        let workspace = workspace.devcontainer(devcontainer).await?;
+        let container_id = workspace.service_container_id()?;
+        // Fit the terminal; scripts reading a pipe get whole commands.
+        let width = std::io::stdout()
+            .is_terminal()
+            .then(|| crossterm::terminal::size().ok())
+            .flatten()
+            .map(|(cols, _)| usize::from(cols));
+        for exec in devcontainer.docker.exec_sessions(container_id).await? {
+            let pid = exec.pid.to_string();
+            // The tab takes us to the next multiple of 8.
+            let width = width.map(|w| w.saturating_sub((pid.len() / 8 + 1) * 8));
+            println!("{pid}\t{}", format_exec(&exec.command, width));
+        }
+        Ok(())
+    }
+}
+
+impl ShowConfig {
+    fn run(self, state: State<'_>) -> eyre::Result<()> {
+        let devcontainer = state.try_devcontainer()?;
+        println!("{}", serde_json::to_string_pretty(&devcontainer.config)?);
+        Ok(())
+    }
+}
+
+/// Shorten an exec's command for display: editor-server paths are cut down to the parts that say
+/// something, and the result is cut to `width` characters, if given.
+fn format_exec(command: &str, width: Option<usize>) -> String {
+    let mut out = command.split(' ').map(shorten_arg).join(" ");
+    if let Some(width) = width
+        && out.chars().count() > width
+    {
+        out = out.chars().take(width.saturating_sub(1)).collect();
+        out.push('…');
+    }
+    out
+}
+
+/// Editor servers live in `~/.<editor>-server/bin/<commit>/`, which is long and says little; show
+/// such paths as `<editor>-server/…/<file>`.
+fn shorten_arg(arg: &str) -> Cow<'_, str> {
+    let server = arg
+        .split('/')
+        .filter_map(|part| part.strip_prefix('.'))
+        .find(|part| part.ends_with("-server") || part.ends_with("-server-insiders"));
+    match (server, arg.rsplit('/').next()) {
+        (Some(server), Some(file)) if arg.contains('/') => format!("{server}/…/{file}").into(),
+        _ => arg.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_exec_shortens_editor_servers() {
+        let command = "/root/.vscode-server/bin/8b3775030ed1a69b13e4f4c628c612102e30a681/node \
+                       /root/.vscode-server/bin/8b3775030ed1a69b13e4f4c628c612102e30a681/out/server-main.js \
+                       --start-server";
+        let command = command.split_whitespace().join(" ");
+        assert_eq!(
+            format_exec(&command, None),
+            "vscode-server/…/node vscode-server/…/server-main.js --start-server"
+        );
+        assert_eq!(format_exec("/bin/bash -l", None), "/bin/bash -l");
+    }
+
+    #[test]
+    fn format_exec_truncates_on_char_boundaries() {
+        assert_eq!(format_exec("echo héllo wörld", Some(9)), "echo hél…");
+        assert_eq!(format_exec("echo hi", Some(7)), "echo hi");
+    }
+}
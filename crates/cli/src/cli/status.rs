@@ -7,6 +7,7 @@ use std::time::Duration;
 use clap::Args;
 use clap_complete::engine::ArgValueCompleter;
 use crossterm::style::Stylize;
+use docker::ContainerStatus;
 
 use crate::bytes::Bytes;
 use crate::cli::status::data::{
@@ -14,14 +15,18 @@ use crate::cli::status::data::{
     Ports, PrevSample, Stats, WsSources,
 };
 use crate::complete::complete_workspace;
-use crate::config::Config;
-use crate::docker::DockerClient;
+use crate::config::DirtyCheck;
+use crate::docker::{DockerClient, Forward};
 use crate::state::State;
-use crate::table::{Align, ColumnDef, Datum, Gatherer, Table, TableBuilder, text, value};
+use crate::table::{
+    Align, BuiltCell, ColumnDef, Datum, Gatherer, Table, TableBuilder, combine, text, value,
+};
 use crate::workspace::Workspace;
 use crate::workspace::git_status::GitStatus;
+use crate::worktree;
 
 mod data;
+mod summary;
 
 const PERIOD: Duration = Duration::from_secs(1);
 
@@ -35,6 +40,27 @@ pub(crate) struct Status {
     /// Show live, updating data
     #[arg(short, long)]
     live: bool,
+
+    /// Show the CPU column, which `--live` always does; it takes two samples per container,
+    /// about a second apart, so this waits that long
+    #[arg(long, conflicts_with = "json")]
+    cpu: bool,
+
+    /// Show every configured project, one table each
+    #[arg(short, long, conflicts_with_all = ["workspace", "live", "json"])]
+    all_projects: bool,
+
+    /// Print a JSON summary per project (every project, unless one is given)
+    #[arg(long, conflicts_with_all = ["workspace", "live"])]
+    json: bool,
+
+    /// Show every column, even if the table is wider than the terminal
+    #[arg(long, conflicts_with = "json")]
+    wide: bool,
+
+    /// Leave out the TOTAL row, which is only shown on a terminal anyway
+    #[arg(long, conflicts_with_all = ["workspace", "json"])]
+    no_summary: bool,
 }
 
 /// A selectable status column. Builds its [`ColumnDef`] from the gathered
@@ -53,10 +79,21 @@ pub(crate) enum Column {
 type GitSources = Arc<HashMap<String, Gatherer<Datum<String>>>>;
 
 /// The NAME column: just the workspace name. Available without Docker.
-fn name_column<'a>() -> ColumnDef<Workspace<'a>> {
-    ColumnDef::new("NAME", Align::Left, |r: &Workspace<'a>| {
-        text(r.name.clone())
-    })
+///
+/// When run from inside a workspace, that row gets a bold `*` marker.
+fn name_column<'a>(current: Option<&str>) -> ColumnDef<Workspace<'a>> {
+    let current = current.map(str::to_owned);
+    ColumnDef::new(
+        "NAME",
+        Align::Left,
+        move |r: &Workspace<'a>| match &current {
+            None => text(r.name.clone()),
+            Some(current) if *current == r.name => {
+                text(format!("{} {}", "*".bold(), r.name.as_str().bold()))
+            }
+            Some(_) => text(format!("  {}", r.name)),
+        },
+    )
 }
 
 /// The GIT column. Fed by the git gatherers, so available without Docker.
@@ -70,12 +107,84 @@ fn git_column<'a>(git: &GitSources) -> ColumnDef<Workspace<'a>> {
 impl Column {
     fn def<'a>(
         self,
+        current: Option<&str>,
+        git: &GitSources,
+        sources: &Arc<HashMap<String, WsSources>>,
+        fwd: &Gatherer<Option<FwdPorts>>,
+    ) -> ColumnDef<Workspace<'a>> {
+        let def = self.make(current, git, sources, fwd);
+        // Narrow terminals get just the name and status.
+        match self {
+            Column::Name | Column::Status => def,
+            _ => def.optional(),
+        }
+    }
+
+    /// This column's cell in the TOTAL row: running and stopped counts, and summed usage.
+    fn total(self, sources: &HashMap<String, WsSources>) -> BuiltCell {
+        match self {
+            Column::Name => text("TOTAL".bold().to_string()),
+            Column::Status => {
+                let statuses = sources
+                    .values()
+                    .map(|s| {
+                        s.info.cell(|i: &Option<Info>| {
+                            i.as_ref()
+                                .map_or(Datum::Pending, |i| Datum::Value(i.status))
+                        })
+                    })
+                    .collect();
+                value(combine(statuses, |statuses| {
+                    let (mut running, mut stopped) = (0, 0);
+                    for status in statuses {
+                        match status {
+                            Datum::Value(Datum::Value(ContainerState(
+                                ContainerStatus::Running,
+                            ))) => {
+                                running += 1;
+                            }
+                            Datum::Value(Datum::Value(_)) => stopped += 1,
+                            _ => {}
+                        }
+                    }
+                    Datum::Value(format!("{running} running, {stopped} stopped"))
+                }))
+            }
+            Column::Mem => {
+                let mems = sources
+                    .values()
+                    .map(|s| {
+                        s.stats
+                            .cell(|s: &Option<Stats>| s.as_ref().map_or(Datum::Pending, |s| s.mem))
+                    })
+                    .collect();
+                value(combine(mems, |mems| {
+                    sum(mems, |Bytes(a), Bytes(b)| Bytes(a + b))
+                }))
+            }
+            Column::Cpu => {
+                let cpus = sources
+                    .values()
+                    .map(|s| {
+                        s.stats
+                            .cell(|s: &Option<Stats>| s.as_ref().map_or(Datum::Pending, |s| s.cpu))
+                    })
+                    .collect();
+                value(combine(cpus, |cpus| sum(cpus, |Cpu(a), Cpu(b)| Cpu(a + b))))
+            }
+            Column::Execs | Column::Ports | Column::Git => text(""),
+        }
+    }
+
+    fn make<'a>(
+        self,
+        current: Option<&str>,
         git: &GitSources,
         sources: &Arc<HashMap<String, WsSources>>,
         fwd: &Gatherer<Option<FwdPorts>>,
     ) -> ColumnDef<Workspace<'a>> {
         match self {
-            Column::Name => name_column(),
+            Column::Name => name_column(current),
             Column::Status => {
                 let sources = sources.clone();
                 ColumnDef::new("STATUS", Align::Left, move |r: &Workspace<'a>| {
@@ -131,16 +240,37 @@ impl Column {
 }
 
 impl Status {
-    pub(crate) async fn run(self, project: Option<String>) -> eyre::Result<()> {
-        let config = Config::load()?;
-        let state = State::new(project, &config).await?;
+    pub(crate) async fn run(self, state: State<'_>) -> eyre::Result<()> {
+        if self.json {
+            return summary::print(&state, !state.project_given).await;
+        }
+        if !self.all_projects {
+            return self.show(&state, false).await;
+        }
+
+        for (i, name) in state.config.projects.keys().enumerate() {
+            if i > 0 {
+                eprintln!();
+            }
+            if *name == state.project_name {
+                self.show(&state, true).await?;
+            } else {
+                let state = State::new(Some(name.to_string()), state.config).await?;
+                self.show(&state, true).await?;
+            }
+        }
+        Ok(())
+    }
 
+    /// Print the status table of `state`'s project, under a header naming it, and its path with
+    /// `with_path`.
+    async fn show(&self, state: &State<'_>, with_path: bool) -> eyre::Result<()> {
         let (table, workspace) = match state.devcontainer.as_ref() {
-            None => (self.git_only_table(&state).await?, None),
+            None => (self.git_only_table(state).await?, None),
             Some(dc) => {
                 let docker = dc.docker.clone();
                 match self.workspace.clone() {
-                    None => (self.workspace_table(&state, docker).await?, None),
+                    None => (self.workspace_table(state, docker).await?, None),
                     Some(name) => {
                         let workspace = state.resolve_workspace(name).await?;
                         (
@@ -152,9 +282,14 @@ impl Status {
             }
         };
 
+        let table = table.wide(self.wide);
         let project = state.project_name.to_string().blue();
 
-        eprintln!("PROJECT: {project}");
+        if with_path {
+            eprintln!("PROJECT: {project} ({})", state.project.path.display());
+        } else {
+            eprintln!("PROJECT: {project}");
+        }
         if let Some(ws) = workspace {
             let ws_name = ws.name.yellow();
             eprintln!("WORKSPACE: {ws_name}")
@@ -173,6 +308,7 @@ impl Status {
         docker: Arc<DockerClient>,
     ) -> eyre::Result<Table> {
         let mut workspaces = Workspace::list(state).await?;
+        let current = current_workspace(state).await;
 
         let fwd = spawn_fwd(docker.clone(), state.project_name.to_string());
 
@@ -201,21 +337,36 @@ impl Status {
             Column::Git,
         ];
 
-        Ok(columns
+        // For speed, exclude CPU (requires at least 1 sec) unless asked for.
+        let columns = columns
             .into_iter()
-            // For speed, exclude CPU (requires at least 1 sec) unless live.
-            .filter(|c| self.live || !matches!(c, Column::Cpu))
-            .map(|c| c.def(&git, &sources, &fwd))
+            .filter(|c| self.show_cpu() || !matches!(c, Column::Cpu))
+            .collect::<Vec<_>>();
+        let table = columns
+            .iter()
+            .map(|c| c.def(current.as_deref(), &git, &sources, &fwd))
             .collect::<TableBuilder<Workspace>>()
-            .build(&workspaces, self.live))
+            .build(&workspaces, self.live);
+        // Scripts reading a pipe want just the workspaces.
+        Ok(if self.no_summary || !std::io::stderr().is_terminal() {
+            table
+        } else {
+            table.footer(columns.iter().map(|c| c.total(&sources)))
+        })
+    }
+
+    /// Whether to show CPU usage, which is only known once sampled twice.
+    fn show_cpu(&self) -> bool {
+        self.live || self.cpu
     }
 
     async fn git_only_table(&self, state: &State<'_>) -> eyre::Result<Table> {
         let mut workspaces = Workspace::list(state).await?;
         workspaces.sort_by(|a, b| b.is_root.cmp(&a.is_root).then_with(|| a.name.cmp(&b.name)));
 
+        let current = current_workspace(state).await;
         let git = build_git(&workspaces);
-        let columns = [name_column(), git_column(&git)];
+        let columns = [name_column(current.as_deref()), git_column(&git)];
         Ok(columns
             .into_iter()
             .collect::<TableBuilder<Workspace>>()
@@ -317,7 +468,7 @@ impl Status {
                 }
             }),
         ];
-        if self.live {
+        if self.show_cpu() {
             let sources = sources.clone();
             columns.push(ColumnDef::new(
                 "CPU",
@@ -339,13 +490,14 @@ impl Status {
             let fwd = fwd.clone();
             move |r: &ContainerRow| {
                 let exposed = r.exposed.clone();
-                value(fwd.cell(move |forwarded: &Option<Vec<u16>>| {
+                value(fwd.cell(move |forwarded: &Option<Vec<Forward>>| {
                     forwarded.as_ref().map_or(Datum::Pending, |forwarded| {
-                        let ports = exposed
+                        let mut ports: Vec<Forward> = forwarded
                             .iter()
                             .copied()
-                            .filter(|p| forwarded.contains(p))
+                            .filter(|p| exposed.contains(&p.container))
                             .collect();
+                        ports.sort_unstable();
                         Datum::Value(Ports(ports))
                     })
                 }))
@@ -359,6 +511,16 @@ impl Status {
     }
 }
 
+/// The name of the workspace containing the cwd, if any.
+async fn current_workspace(state: &State<'_>) -> Option<String> {
+    state
+        .try_resolve_workspace(None)
+        .await
+        .ok()
+        .flatten()
+        .map(|ws| ws.name)
+}
+
 fn spawn_fwd(docker: Arc<DockerClient>, project: String) -> Gatherer<Option<FwdPorts>> {
     Gatherer::spawn(PERIOD, move || {
         let docker = docker.clone();
@@ -372,16 +534,23 @@ fn build_git(workspaces: &[Workspace<'_>]) -> GitSources {
     Arc::new(
         workspaces
             .iter()
-            .map(|ws| (ws.name.clone(), spawn_git(ws.path.clone())))
+            .map(|ws| {
+                let check = ws.state.project.dirty_check.clone();
+                (ws.name.clone(), spawn_git(ws.path.clone(), check))
+            })
             .collect(),
     )
 }
 
-fn spawn_git(path: PathBuf) -> Gatherer<Datum<String>> {
+fn spawn_git(path: PathBuf, check: DirtyCheck) -> Gatherer<Datum<String>> {
     Gatherer::spawn(PERIOD, move || {
         let path = path.clone();
+        let check = check.clone();
         async move {
-            GitStatus::fetch(&path)
+            if worktree::is_broken(&path) {
+                return Datum::Value(format!("{} (see `dc prune`)", "broken".red()));
+            }
+            GitStatus::fetch(&path, &check)
                 .await
                 .map(|g| Datum::Value(g.to_string()))
                 .unwrap_or(Datum::NotApplicable)
@@ -389,6 +558,17 @@ fn spawn_git(path: PathBuf) -> Gatherer<Datum<String>> {
     })
 }
 
+/// Add up the values among `data`; not applicable if there are none.
+fn sum<V>(data: Vec<Datum<V>>, add: impl Fn(V, V) -> V) -> Datum<V> {
+    data.into_iter()
+        .filter_map(|d| match d {
+            Datum::Value(v) => Some(v),
+            Datum::Pending | Datum::NotApplicable => None,
+        })
+        .reduce(add)
+        .map_or(Datum::NotApplicable, Datum::Value)
+}
+
 /// The per-workspace Docker gatherers. `stats`/`execs` derive off `info` to
 /// reuse the ids it discovers, so each runs independently without re-enumerating.
 fn build_sources(docker: Arc<DockerClient>, compose_project: String) -> WsSources {
@@ -457,10 +637,7 @@ async fn poll_stats(
 
     let mut prev = prev.lock().unwrap();
     let mut mem_bytes = 0u64;
-    let mut cpu_delta = 0u64;
-    let mut system_prev = None;
-    let mut system_now = None;
-    let mut cpus = 1u32;
+    let mut cpu: Option<f64> = None;
     let mut have_sample = false;
     for (id, sample) in &samples {
         let Some(sample) = sample else {
@@ -468,23 +645,10 @@ async fn poll_stats(
         };
         have_sample = true;
         mem_bytes += sample.ram;
-        if let Some(p) = prev.get(id) {
-            cpu_delta += sample.cpu_total.saturating_sub(p.total);
-            system_prev = Some(p.system);
-        }
-        if let Some(system) = sample.system_cpu {
-            system_now = Some(system);
+        if let Some(percent) = prev.get(id).and_then(|p| p.cpu_percent(sample)) {
+            *cpu.get_or_insert(0.0) += percent;
         }
-        if let Some(c) = sample.online_cpus {
-            cpus = c;
-        }
-        prev.insert(
-            id.clone(),
-            PrevSample {
-                total: sample.cpu_total,
-                system: sample.system_cpu.unwrap_or(0),
-            },
-        );
+        prev.insert(id.clone(), PrevSample::from(sample));
     }
 
     if !have_sample {
@@ -494,12 +658,10 @@ async fn poll_stats(
         });
     }
 
-    let cpu = match (system_prev, system_now) {
-        (Some(sp), Some(sn)) if sn > sp => Datum::Value(Cpu(cpu_delta as f64 / (sn - sp) as f64
-            * f64::from(cpus)
-            * 100.0)),
+    let cpu = match cpu {
+        Some(percent) => Datum::Value(Cpu(percent)),
         // Only one sample so far: pending.
-        _ => Datum::Pending,
+        None => Datum::Pending,
     };
 
     Some(Stats {
@@ -564,21 +726,12 @@ async fn poll_container_stats(
     };
 
     let mut prev = prev.lock().unwrap();
-    let cpu = match (*prev, sample.system_cpu) {
-        (Some(p), Some(system_now)) if system_now > p.system => {
-            let delta = sample.cpu_total.saturating_sub(p.total);
-            let cpus = f64::from(sample.online_cpus.unwrap_or(1));
-            Datum::Value(Cpu(delta as f64 / (system_now - p.system) as f64
-                * cpus
-                * 100.0))
-        }
+    let cpu = match prev.and_then(|p| p.cpu_percent(&sample)) {
+        Some(percent) => Datum::Value(Cpu(percent)),
         // Only one sample so far: pending.
-        _ => Datum::Pending,
+        None => Datum::Pending,
     };
-    *prev = Some(PrevSample {
-        total: sample.cpu_total,
-        system: sample.system_cpu.unwrap_or(0),
-    });
+    *prev = Some(PrevSample::from(&sample));
 
     Some(Stats {
         mem: Datum::Value(Bytes(sample.ram)),
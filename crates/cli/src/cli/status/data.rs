@@ -1,10 +1,11 @@
-use std::{collections::HashMap, fmt};
+use std::{collections::HashMap, fmt, time::Instant};
 
 use docker::ContainerStatus;
 
 use crate::{
     ansi::{BLUE, GREEN, RED, RESET, YELLOW},
     bytes::Bytes,
+    docker::{Forward, StatsSample},
     table::{Datum, Gatherer},
 };
 
@@ -82,14 +83,17 @@ impl fmt::Display for Execs {
     }
 }
 
-/// Forwarded (`dc fwd`) ports.
-pub(crate) struct Ports(pub Vec<u16>);
+/// Forwarded (`dc fwd`) ports, as `host->container` when `--auto-port` moved one.
+pub(crate) struct Ports(pub Vec<Forward>);
 
 impl fmt::Display for Ports {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         for (i, p) in self.0.iter().enumerate() {
             let sep = if i == 0 { "" } else { "," };
-            write!(f, "{sep}{BLUE}{p}{RESET}")?;
+            write!(f, "{sep}{BLUE}{}{RESET}", p.host)?;
+            if p.host != p.container {
+                write!(f, "->{}", p.container)?;
+            }
         }
         Ok(())
     }
@@ -112,7 +116,146 @@ pub(crate) struct Stats {
 #[derive(Clone, Copy)]
 pub(crate) struct PrevSample {
     pub total: u64,
-    pub system: u64,
+    pub system: Option<u64>,
+    pub at: Instant,
 }
 
-pub(crate) type FwdPorts = HashMap<String, Vec<u16>>;
+impl From<&StatsSample> for PrevSample {
+    fn from(sample: &StatsSample) -> Self {
+        Self {
+            total: sample.cpu_total,
+            system: sample.system_cpu,
+            at: sample.at,
+        }
+    }
+}
+
+impl PrevSample {
+    /// CPU use since this sample, as a percentage of one core, clamped to `[0, cores * 100]`.
+    ///
+    /// Diffs against the host-wide counter when both samples have it. Without it (some cgroup v2
+    /// setups), the wall time between samples stands in: the host counter sums that over cores.
+    /// `None` if the counters haven't moved yet.
+    pub fn cpu_percent(&self, now: &StatsSample) -> Option<f64> {
+        let cpus = f64::from(now.cpus.unwrap_or(1));
+        let used = now.cpu_total.saturating_sub(self.total) as f64;
+        let percent = match (self.system, now.system_cpu) {
+            (Some(prev), Some(system)) => {
+                if system <= prev {
+                    return None;
+                }
+                used / (system - prev) as f64 * cpus * 100.0
+            }
+            _ => {
+                let elapsed = now.at.saturating_duration_since(self.at).as_nanos();
+                if elapsed == 0 {
+                    return None;
+                }
+                used / elapsed as f64 * 100.0
+            }
+        };
+        Some(percent.clamp(0.0, cpus * 100.0))
+    }
+}
+
+pub(crate) type FwdPorts = HashMap<String, Vec<Forward>>;
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serde_json::json;
+
+    use super::*;
+
+    fn sample(stats: serde_json::Value) -> StatsSample {
+        StatsSample::new(serde_json::from_value(stats).unwrap())
+    }
+
+    /// `now`, taken `secs` after `prev`.
+    fn after(prev: &StatsSample, secs: u64, now: serde_json::Value) -> StatsSample {
+        StatsSample {
+            at: prev.at + Duration::from_secs(secs),
+            ..sample(now)
+        }
+    }
+
+    #[test]
+    fn cpu_percent_cgroup_v1_counts_cores_from_percpu_usage() {
+        let prev = sample(json!({ "cpu_stats": {
+            "cpu_usage": { "total_usage": 1_000_000_000u64, "percpu_usage": [1, 2, 3, 4] },
+            "system_cpu_usage": 100_000_000_000u64,
+        } }));
+        // One core busy for 2s of a 4-core host.
+        let now = after(
+            &prev,
+            2,
+            json!({ "cpu_stats": {
+            "cpu_usage": { "total_usage": 3_000_000_000u64, "percpu_usage": [1, 2, 3, 4] },
+            "system_cpu_usage": 108_000_000_000u64,
+        } }),
+        );
+        assert_eq!(now.cpus, Some(4));
+        assert_eq!(PrevSample::from(&prev).cpu_percent(&now), Some(100.0));
+    }
+
+    #[test]
+    fn cpu_percent_cgroup_v2() {
+        let prev = sample(json!({ "cpu_stats": {
+            "cpu_usage": { "total_usage": 0 },
+            "system_cpu_usage": 100_000_000_000u64,
+            "online_cpus": 8,
+        } }));
+        let now = after(
+            &prev,
+            2,
+            json!({ "cpu_stats": {
+            "cpu_usage": { "total_usage": 4_000_000_000u64 },
+            "system_cpu_usage": 116_000_000_000u64,
+            "online_cpus": 8,
+        } }),
+        );
+        assert_eq!(PrevSample::from(&prev).cpu_percent(&now), Some(200.0));
+    }
+
+    #[test]
+    fn cpu_percent_without_system_usage_uses_wall_time() {
+        let prev = sample(json!({ "cpu_stats": { "cpu_usage": { "total_usage": 0 } } }));
+        assert_eq!(prev.cpus, None);
+        let now = after(
+            &prev,
+            2,
+            json!({ "cpu_stats": { "cpu_usage": { "total_usage": 1_000_000_000u64 } } }),
+        );
+        assert_eq!(PrevSample::from(&prev).cpu_percent(&now), Some(50.0));
+    }
+
+    #[test]
+    fn cpu_percent_is_clamped_to_cores() {
+        let prev = sample(json!({ "cpu_stats": {
+            "cpu_usage": { "total_usage": 0 },
+            "online_cpus": 2,
+        } }));
+        let now = after(
+            &prev,
+            2,
+            json!({ "cpu_stats": {
+            "cpu_usage": { "total_usage": 5_000_000_000u64 },
+            "online_cpus": 2,
+        } }),
+        );
+        assert_eq!(PrevSample::from(&prev).cpu_percent(&now), Some(200.0));
+    }
+
+    #[test]
+    fn cpu_percent_waits_for_the_system_counter_to_move() {
+        let stats = json!({ "cpu_stats": {
+            "cpu_usage": { "total_usage": 0 },
+            "system_cpu_usage": 100,
+            "online_cpus": 1,
+        } });
+        let prev = sample(stats.clone());
+        let now = after(&prev, 1, stats);
+        assert_eq!(PrevSample::from(&prev).cpu_percent(&now), None);
+    }
+}
@@ -0,0 +1,106 @@
+//! `dc status --json`: a per-project roll-up for dashboards and status bars.
+
+use futures::future::try_join_all;
+use serde::Serialize;
+
+use crate::config::ProjectName;
+use crate::docker::DockerClient;
+use crate::state::State;
+use crate::workspace::Workspace;
+
+#[derive(Debug, Default, Serialize)]
+struct ProjectSummary {
+    name: String,
+    workspaces: usize,
+    /// Workspaces with at least one running container.
+    running: usize,
+    /// Workspaces with containers, none of them running.
+    stopped: usize,
+    dirty: usize,
+    /// Memory in use across all running containers, in bytes.
+    ram: u64,
+}
+
+#[derive(Debug, Default)]
+struct WorkspaceSummary {
+    running: bool,
+    stopped: bool,
+    dirty: bool,
+    ram: u64,
+}
+
+/// Print the roll-up for the given project, or for every configured project when `all` is set.
+pub(super) async fn print(state: &State<'_>, all: bool) -> eyre::Result<()> {
+    let summaries = if all {
+        try_join_all(
+            state
+                .config
+                .projects
+                .keys()
+                .map(|name| project_summary(state, name)),
+        )
+        .await?
+    } else {
+        vec![summarize(state).await?]
+    };
+    println!("{}", serde_json::to_string_pretty(&summaries)?);
+    Ok(())
+}
+
+async fn project_summary(state: &State<'_>, name: &ProjectName) -> eyre::Result<ProjectSummary> {
+    if *name == state.project_name {
+        return summarize(state).await;
+    }
+    let state = State::new(Some(name.to_string()), state.config).await?;
+    summarize(&state).await
+}
+
+async fn summarize(state: &State<'_>) -> eyre::Result<ProjectSummary> {
+    let workspaces = Workspace::list(state).await?;
+    let docker = state.devcontainer.as_ref().map(|dc| &*dc.docker);
+    let per_workspace =
+        try_join_all(workspaces.iter().map(|ws| summarize_workspace(ws, docker))).await?;
+
+    let mut summary = ProjectSummary {
+        name: state.project_name.to_string(),
+        workspaces: per_workspace.len(),
+        ..Default::default()
+    };
+    for ws in per_workspace {
+        summary.running += usize::from(ws.running);
+        summary.stopped += usize::from(ws.stopped);
+        summary.dirty += usize::from(ws.dirty);
+        summary.ram += ws.ram;
+    }
+    Ok(summary)
+}
+
+async fn summarize_workspace(
+    workspace: &Workspace<'_>,
+    docker: Option<&DockerClient>,
+) -> eyre::Result<WorkspaceSummary> {
+    let dirty = workspace.git_status().await?.is_dirty();
+    let Some(docker) = docker else {
+        return Ok(WorkspaceSummary {
+            dirty,
+            ..Default::default()
+        });
+    };
+
+    let containers = docker
+        .compose_container_info(&workspace.compose_project_name())
+        .await?;
+    let running: Vec<&str> = containers
+        .iter()
+        .filter(|c| c.state == docker::ContainerStatus::Running)
+        .map(|c| c.id.as_str())
+        .collect();
+    let samples = try_join_all(running.iter().map(|id| docker.stats_sample(id))).await?;
+
+    Ok(WorkspaceSummary {
+        running: !running.is_empty(),
+        stopped: running.is_empty() && !containers.is_empty(),
+        dirty,
+        ram: samples.iter().map(|s| s.ram).sum(),
+    })
+}
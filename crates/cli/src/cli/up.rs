@@ -1,22 +1,31 @@
+use std::cell::Cell;
+
 use clap::Args;
 use clap_complete::ArgValueCompleter;
 use color_eyre::owo_colors::OwoColorize;
-use indexmap::IndexMap;
 use tracing::info_span;
 use tracing_indicatif::span_ext::IndicatifSpanExt;
 
-use crate::cli::exec::exec_interactive;
+use crate::ansi::{RESET, YELLOW};
+use crate::cli::exec::{ExecInput, exec_interactive};
 use crate::cli::fwd::forward;
-use crate::cli::{State, go, proxy};
+use crate::cli::{State, confirm, destroy, go, proxy};
 use crate::complete::complete_workspace;
-use crate::config::Config;
+use crate::devcontainer::dc_options::PullPolicy;
 use crate::devcontainer::substitution;
-use crate::docker::compose::{compose_cmd, compose_ps_q};
+use crate::docker::compose::{check_services, compose_cmd_publishing, compose_ps_q, config_drift};
 use crate::docker::probe;
 use crate::run::Runner;
 use crate::run::cmd::NamedCmd;
+use crate::run::docker_exec::ExecContext;
+use crate::state::{DevcontainerState, is_path_arg};
+use crate::subscriber::{emit_json, json_progress};
+use crate::workspace::Workspace;
+use crate::workspace::metadata::Metadata;
 use crate::worktree;
 
+mod plan;
+
 /// Bring up a workspace, creating it if it does not exist
 #[derive(Debug, Args)]
 pub(crate) struct Up {
@@ -24,6 +33,15 @@ pub(crate) struct Up {
     #[arg(short, long)]
     forward: bool,
 
+    /// With `--forward`, move ports that are already taken to a free host port
+    #[arg(long, requires = "forward")]
+    auto_port: bool,
+
+    /// Publish `forwardPorts` from the containers themselves, as compose `ports`, rather than
+    /// through `dc fwd`; compose recreates the containers this changes
+    #[arg(long, conflicts_with_all = ["forward", "no_recreate"])]
+    publish: bool,
+
     /// Detach worktree rather than creating a branch
     #[arg(short, long)]
     detach: bool,
@@ -32,21 +50,140 @@ pub(crate) struct Up {
     #[arg(short, long)]
     go: bool,
 
-    /// Workspace name
+    /// Recreate the containers even if their configuration is unchanged
+    #[arg(long)]
+    recreate: bool,
+
+    /// Keep existing containers even if devcontainer.json has changed
+    #[arg(long, conflicts_with = "recreate")]
+    no_recreate: bool,
+
+    /// Remove the workspace's containers and named volumes first, keeping the worktree, to start
+    /// over with fresh data
+    #[arg(long, conflicts_with = "no_recreate")]
+    recreate_volumes: bool,
+
+    /// Don't ask before removing volumes with `--recreate-volumes`
+    #[arg(short, long, requires = "recreate_volumes")]
+    yes: bool,
+
+    /// Only create the worktree and run `initializeCommand`, without starting any containers
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "forward", "publish", "recreate", "no_recreate", "recreate_volumes", "pull",
+            "build_arg", "exec",
+        ],
+    )]
+    only_initialize: bool,
+
+    /// When to pull images before building [default: configured pull, else missing]
+    #[arg(long, value_enum)]
+    pull: Option<PullPolicy>,
+
+    /// Build images with this `KEY=VALUE` build arg, or `KEY` to take its value from the
+    /// environment; repeatable. Containers on images this changes are recreated
+    #[arg(long, value_name = "KEY=VALUE")]
+    build_arg: Vec<String>,
+
+    /// Print what would be done, including the compose override, without doing any of it
+    #[arg(short = 'n', long)]
+    dry_run: bool,
+
+    /// Workspace name, or path to an existing worktree of the project (e.g. `./foo`)
     #[arg(add = ArgValueCompleter::new(complete_workspace))]
     workspace: Option<String>,
 
-    /// Exec once up with the given command [default: configured defaultExec]
+    /// Exec once up with the given command [default: configured defaultExec, else a login shell]
     #[arg(short = 'x', long, num_args = 0.., allow_hyphen_values = true)]
     exec: Option<Vec<String>>,
 }
 
 impl Up {
-    pub(crate) async fn run(self, project: Option<String>) -> eyre::Result<()> {
-        let config = Config::load()?;
-        let state = State::new(project, &config).await?;
-        let workspace = state.resolve_workspace(self.workspace).await?;
+    pub(crate) fn needs_docker(&self) -> bool {
+        !self.only_initialize && !self.dry_run
+    }
+
+    /// The `compose up` flag for `--recreate` or `--no-recreate`. Without either, compose
+    /// recreates what changed.
+    fn recreate_flag(&self) -> Option<&'static str> {
+        if self.recreate {
+            Some("--force-recreate")
+        } else if self.no_recreate {
+            Some("--no-recreate")
+        } else {
+            None
+        }
+    }
+
+    /// An `up` that creates the workspace and then navigates to it, as for `dc go --create`.
+    pub(crate) fn create_and_go(workspace: String) -> Self {
+        Self {
+            forward: false,
+            auto_port: false,
+            publish: false,
+            detach: false,
+            go: true,
+            recreate: false,
+            no_recreate: false,
+            recreate_volumes: false,
+            yes: false,
+            only_initialize: false,
+            pull: None,
+            build_arg: Vec::new(),
+            dry_run: false,
+            workspace: Some(workspace),
+            exec: None,
+        }
+    }
+
+    pub(crate) async fn run(mut self, state: State<'_>) -> eyre::Result<()> {
+        // Worktrees given by path are managed externally; leave them be.
+        let external = self.workspace.as_deref().is_some_and(is_path_arg);
+        let workspace = state.resolve_new_workspace(self.workspace.take()).await?;
+        if self.dry_run {
+            return self.plan(&state, &workspace).await;
+        }
+        // Only a worktree this run creates is ours to remove if interrupted.
+        let created = !workspace.is_root && !external && !workspace.path.exists();
+
+        if self.recreate_volumes && !created && !self.yes {
+            eprintln!(
+                "{YELLOW}Will remove the volumes of workspace {} — DATA WILL BE LOST{RESET}",
+                workspace.name
+            );
+            if !confirm()? {
+                eprintln!("Aborted.");
+                return Ok(());
+            }
+        }
+
+        // Set once `up` has notified itself, before an `--exec`.
+        let notified = Cell::new(false);
+        let result = tokio::select! {
+            result = self.up(&state, &workspace, external, &notified) => result,
+            _ = tokio::signal::ctrl_c() => {
+                if created {
+                    eprintln!("Interrupted; removing {}", workspace.name);
+                    let devcontainer = state.devcontainer_for(&workspace.path).ok();
+                    destroy::destroy_new(devcontainer.as_ref(), &workspace).await?;
+                }
+                Err(eyre::eyre!("interrupted"))
+            }
+        };
+        if !notified.get() {
+            notify(&state, &workspace, result.is_ok()).await;
+        }
+        result
+    }
 
+    async fn up(
+        self,
+        state: &State<'_>,
+        workspace: &Workspace<'_>,
+        external: bool,
+        notified: &Cell<bool>,
+    ) -> eyre::Result<()> {
         // Set up span.
         let name = &workspace.name;
         let colored_name = name.cyan().to_string();
@@ -69,9 +206,22 @@ impl Up {
         );
         span.pb_set_message(&pb_message);
         let _guard = span.enter();
+        // Show the active phase next to the spinner; the phases themselves get child spans.
+        let phase = |phase: &str| {
+            if json_progress() {
+                emit_json(
+                    serde_json::json!({ "event": "phase", "workspace": name, "phase": phase }),
+                );
+            }
+            span.pb_set_message(&format!("{pb_message}: {phase}"));
+        };
 
-        if !workspace.is_root {
-            worktree::create(&workspace, self.detach).await?;
+        if !workspace.is_root && !external {
+            phase("worktree");
+            worktree::create(workspace, self.detach).await?;
+            if state.project.workspace_metadata {
+                Metadata::write(workspace).await?;
+            }
         }
 
         if !state.has_devcontainer() {
@@ -83,44 +233,91 @@ impl Up {
 
         // initializeCommand runs on the host, from the worktree
         if let Some(ref cmd) = devcontainer.config.initialize_command {
-            cmd.run_on_host("initializeCommand", Some(&workspace.path))
-                .await?;
+            phase("initializeCommand");
+            let dir = devcontainer
+                .devconcurrent()
+                .lifecycle_workdir(&workspace.path)?;
+            cmd.run_on_host("initializeCommand", Some(&dir)).await?;
+        }
+        if self.only_initialize {
+            return Ok(());
         }
 
         // If proxy is configured for this project, make sure the proxy
         // container is running before compose-up so it can react to start
         // events.
         if devcontainer.proxy_enabled() {
-            let proxy = proxy::ProxyState::from_workspace(&config, Some(&workspace)).await?;
+            let proxy = proxy::ProxyState::from_workspace(state.config, Some(workspace)).await?;
             proxy::ensure_up(proxy).await?;
         }
 
-        let mut compose_up_cmd = compose_cmd(devcontainer, &workspace)?;
-        compose_up_cmd.args(["up", "-d", "--build", "--remove-orphans"]);
+        let services = check_services(devcontainer, workspace).await?;
+
+        // The sidecars are tied to the container they forward to, so a
+        // recreated container leaves them dangling; remember to redo them.
+        let had_forwards = devcontainer.docker.has_forward_sidecars(workspace).await?;
+
+        if self.recreate_volumes {
+            phase("docker compose down -v");
+            let mut down_cmd = compose_cmd_publishing(devcontainer, workspace, self.publish)?;
+            down_cmd.args(["down", "-v", "--remove-orphans"]);
+            let down_cmd = down_cmd.as_std().into();
+            Runner::run(NamedCmd {
+                name: "docker compose down -v",
+                cmd: &down_cmd,
+                dir: None,
+            })
+            .await?;
+        }
+
+        let mut compose_up_cmd = compose_cmd_publishing(devcontainer, workspace, self.publish)?;
+        let pull = self.pull.unwrap_or(devcontainer.devconcurrent().pull);
+        tracing::info!("pulling images: {}", pull.as_str());
+        compose_up_cmd.args(["up", "-d", "--remove-orphans", "--pull", pull.as_str()]);
+        // `compose up` takes no build args, so those builds happen first, on their own.
+        if self.build_arg.is_empty() {
+            compose_up_cmd.arg("--build");
+        } else {
+            phase("docker compose build");
+            build_with_args(devcontainer, workspace, &self.build_arg, pull, self.publish).await?;
+        }
+        compose_up_cmd.args(self.recreate_flag());
+        // Compose decides whether to recreate from its own config hash; this only says why.
+        let before = primary_container(devcontainer, workspace).await?;
+        if let Some(before) = &before
+            && !self.recreate
+            && !self.no_recreate
+        {
+            report_drift(devcontainer, workspace, before).await?;
+        }
 
-        if let Some(ref services) = devcontainer.config.run_services {
+        if let Some(services) = services {
             compose_up_cmd.args(services);
-            if !services.contains(&devcontainer.config.service) {
-                // TODO: We probably want this in the `else` also, or maybe we
-                // don't need it at all?
-                compose_up_cmd.arg(&devcontainer.config.service);
-            }
         }
 
-        let up_cmd = compose_up_cmd.into_std().into();
+        phase("docker compose up");
+        let up_cmd = compose_up_cmd.as_std().into();
         let cmd = NamedCmd {
             name: "docker compose up",
             cmd: &up_cmd,
             dir: None,
         };
         Runner::run(cmd).await?;
+        compose_up_cmd.keep();
 
-        let container_id = compose_ps_q(devcontainer, &workspace).await?;
+        let container_id = compose_ps_q(devcontainer, workspace).await?;
+        // A new container, whether created or recreated, gets the create-only commands.
+        let recreate = before.as_deref() != Some(container_id.as_str());
         let user = devcontainer.config.remote_user.as_deref();
-        let workdir = Some(devcontainer.config.workspace_folder.as_path());
+        let workdir = devcontainer
+            .devconcurrent()
+            .lifecycle_workdir(&devcontainer.config.workspace_folder)?;
+        let workdir = Some(workdir.as_path());
+        let login_shell = devcontainer.devconcurrent().login_shell;
 
         let container =
-            probe::ContainerData::inspect(&devcontainer.docker.client, &container_id).await?;
+            probe::ContainerData::inspect(devcontainer.docker.client().await?, &container_id)
+                .await?;
         let probed = probe::user_env(
             &container_id,
             user,
@@ -131,54 +328,62 @@ impl Up {
         let context =
             substitution::Context::new(&workspace.path, &devcontainer.config.workspace_folder)
                 .with_container(container);
-        // Spec merge order: probed env is the base; devcontainer.json `remoteEnv` overlays.
-        // A `None` (spec `null`) emits `-e KEY=` (empty) downstream.
-        let mut merged: IndexMap<String, Option<String>> =
-            probed.into_iter().map(|(k, v)| (k, Some(v))).collect();
-        for (key, template) in &devcontainer.config.remote_env {
-            merged.insert(key.clone(), template.as_ref().map(|t| t.render(&context)));
-        }
-        let remote_env = &merged;
-
-        // Lifecycle commands: create-only commands run only on first creation
-        // For now, though, we always recreate.
-        if let Some(ref cmd) = devcontainer.config.on_create_command {
-            cmd.run_in_container("onCreateCommand", &container_id, user, workdir, remote_env)
+        let remote_env = &devcontainer.config.exec_env(probed, &context);
+        let secrets = &devcontainer.config.secret_env(&state.project.path)?;
+        let exec_context = ExecContext {
+            container: &container_id,
+            user,
+            workdir,
+            login_shell,
+            env: remote_env,
+            secrets,
+        };
+
+        // Lifecycle commands: create-only commands run only when the container is new.
+        if recreate && let Some(ref cmd) = devcontainer.config.on_create_command {
+            phase("onCreateCommand");
+            cmd.run_in_container("onCreateCommand", exec_context)
                 .await?;
         }
-        if let Some(ref cmd) = devcontainer.config.update_content_command {
-            cmd.run_in_container(
-                "updateContentCommand",
-                &container_id,
-                user,
-                workdir,
-                remote_env,
-            )
-            .await?;
+        if recreate && let Some(ref cmd) = devcontainer.config.update_content_command {
+            phase("updateContentCommand");
+            cmd.run_in_container("updateContentCommand", exec_context)
+                .await?;
         }
-        if let Some(ref cmd) = devcontainer.config.post_create_command {
-            cmd.run_in_container(
-                "postCreateCommand",
-                &container_id,
-                user,
-                workdir,
-                remote_env,
-            )
-            .await?;
+        if recreate && let Some(ref cmd) = devcontainer.config.post_create_command {
+            phase("postCreateCommand");
+            cmd.run_in_container("postCreateCommand", exec_context)
+                .await?;
         }
         if let Some(ref cmd) = devcontainer.config.post_start_command {
-            cmd.run_in_container("postStartCommand", &container_id, user, workdir, remote_env)
+            phase("postStartCommand");
+            cmd.run_in_container("postStartCommand", exec_context)
                 .await?;
         }
 
-        // Port forward if requested
-        if self.forward {
-            forward(devcontainer, &workspace).await?;
+        // Port forward if requested, or if this workspace was forwarding before
+        if self.forward || had_forwards {
+            phase("forwarding ports");
+            forward(devcontainer, workspace, self.auto_port, None).await?;
         }
 
         // Interactive exec if requested
         if let Some(cmd_args) = self.exec {
-            exec_interactive(&container_id, devcontainer, remote_env, &cmd_args)?;
+            // The exec replaces us, so this is our last chance.
+            notify(state, workspace, true).await;
+            notified.set(true);
+            exec_interactive(
+                &container_id,
+                devcontainer,
+                remote_env,
+                secrets,
+                &cmd_args,
+                &devcontainer.config.workspace_folder,
+                ExecInput::Inherit {
+                    tty: true,
+                    clear: false,
+                },
+            )?;
         }
 
         if self.go {
@@ -188,3 +393,98 @@ impl Up {
         Ok(())
     }
 }
+
+/// Run the configured `notifyCommand`, if any, to say `up` is done. Its failure is only warned
+/// about, so it can't mask how `up` went.
+async fn notify(state: &State<'_>, workspace: &Workspace<'_>, succeeded: bool) {
+    // An interrupted create removes the worktree, and its config with it.
+    let devcontainer = state.devcontainer_for(&workspace.path).ok();
+    let Some(cmd) = devcontainer
+        .as_ref()
+        .or(state.devcontainer.as_ref())
+        .and_then(|dc| dc.devconcurrent().notify_command.as_ref())
+    else {
+        return;
+    };
+    let dir = if workspace.path.exists() {
+        &workspace.path
+    } else {
+        &state.project.path
+    };
+
+    let args = cmd.as_args();
+    let status = tokio::process::Command::new(args[0])
+        .args(&args[1..])
+        .env("DC_WORKSPACE", &workspace.name)
+        .env("DC_STATUS", if succeeded { "success" } else { "failure" })
+        .current_dir(dir)
+        .status()
+        .await;
+    match status {
+        Ok(status) if status.success() => {}
+        Ok(status) => tracing::warn!("notifyCommand failed: {status}"),
+        Err(e) => tracing::warn!("failed to run notifyCommand: {e}"),
+    }
+}
+
+/// Build the workspace's images with `build_args`, for `compose up` to then use as they are.
+async fn build_with_args(
+    devcontainer: &DevcontainerState,
+    workspace: &Workspace<'_>,
+    build_args: &[String],
+    pull: PullPolicy,
+    publish: bool,
+) -> eyre::Result<()> {
+    // Publishing too, as this rewrites the override `compose up` reads.
+    let mut build = compose_cmd_publishing(devcontainer, workspace, publish)?;
+    build.arg("build");
+    if pull == PullPolicy::Always {
+        build.arg("--pull");
+    }
+    for arg in build_args {
+        build.arg("--build-arg").arg(arg);
+    }
+
+    let build_cmd = build.as_std().into();
+    Runner::run(NamedCmd {
+        name: "docker compose build",
+        cmd: &build_cmd,
+        dir: None,
+    })
+    .await
+}
+
+/// The id of the workspace's primary container, if it has one.
+async fn primary_container(
+    devcontainer: &DevcontainerState,
+    workspace: &Workspace<'_>,
+) -> eyre::Result<Option<String>> {
+    let service = devcontainer.config.service.as_str();
+    Ok(devcontainer
+        .docker
+        .compose_container_info(&workspace.compose_project_name())
+        .await?
+        .into_iter()
+        .find(|c| c.service.as_deref() == Some(service))
+        .map(|c| c.id))
+}
+
+/// Say how the primary container `id` differs from devcontainer.json, as far as
+/// [`config_drift`] can tell.
+async fn report_drift(
+    devcontainer: &DevcontainerState,
+    workspace: &Workspace<'_>,
+    id: &str,
+) -> eyre::Result<()> {
+    let service = devcontainer.config.service.as_str();
+    let details = devcontainer
+        .docker
+        .client()
+        .await?
+        .inspect_container(id)
+        .await?;
+    for change in config_drift(devcontainer, workspace, &details) {
+        tracing::info!("{service}: {change}");
+    }
+    Ok(())
+}
@@ -0,0 +1,264 @@
+//! `dc up --dry-run`: print what `up` would do, without touching Docker or creating anything.
+
+use std::fmt::Write as _;
+
+use crate::cli::up::Up;
+use crate::devcontainer::dc_options::PullPolicy;
+use crate::devcontainer::lifecycle_command::LifecycleCommand;
+use crate::docker::compose::{
+    compose_config, compose_override, describe_compose_cmd, services_to_start,
+};
+use crate::run::cmd::Cmd;
+use crate::state::{DevcontainerState, State};
+use crate::workspace::Workspace;
+
+impl Up {
+    pub(super) async fn plan(
+        &self,
+        state: &State<'_>,
+        workspace: &Workspace<'_>,
+    ) -> eyre::Result<()> {
+        let mut s = String::new();
+        let path = workspace.path.display();
+        let worktree = if workspace.is_root {
+            "the project root"
+        } else if workspace.path.exists() {
+            "existing"
+        } else if self.detach {
+            "new, detached"
+        } else {
+            "new, on a branch of the same name"
+        };
+        writeln!(s, "Workspace: {} at {path} ({worktree})", workspace.name)?;
+
+        if !state.has_devcontainer() {
+            s.push_str("\nNo devcontainer; only the worktree would be created.\n");
+            print!("{s}");
+            return Ok(());
+        }
+        let devcontainer = planned_devcontainer(state, workspace)?;
+        let config = &devcontainer.config;
+        let host_dir = devcontainer
+            .devconcurrent()
+            .lifecycle_workdir(&workspace.path)?;
+        let container_dir = devcontainer
+            .devconcurrent()
+            .lifecycle_workdir(&config.workspace_folder)?;
+
+        s.push_str("\nSteps:\n");
+        let mut step = 0;
+        let mut push = |s: &mut String, line: String| {
+            step += 1;
+            writeln!(s, "{step:>3}. {line}")
+        };
+        if let Some(cmd) = &config.initialize_command {
+            push(
+                &mut s,
+                format!(
+                    "initializeCommand, on the host in {}: {}",
+                    host_dir.display(),
+                    describe(cmd)
+                ),
+            )?;
+        }
+        if self.only_initialize {
+            print!("{s}");
+            return Ok(());
+        }
+
+        let compose = describe_compose_cmd(&devcontainer, workspace)?;
+        if self.recreate_volumes {
+            push(&mut s, format!("{compose} down -v --remove-orphans"))?;
+        }
+        let pull = self.pull.unwrap_or(devcontainer.devconcurrent().pull);
+        if !self.build_arg.is_empty() {
+            let mut build = format!("{compose} build");
+            if pull == PullPolicy::Always {
+                build.push_str(" --pull");
+            }
+            for arg in &self.build_arg {
+                build.push_str(&format!(" --build-arg {arg}"));
+            }
+            push(&mut s, build)?;
+        }
+        // A new worktree's compose files aren't checked out yet; the root's are the same.
+        let source = if workspace.path.exists() {
+            &devcontainer
+        } else {
+            state.try_devcontainer()?
+        };
+        let project = compose_config(source, workspace).await?;
+        let services = services_to_start(config, project.as_ref())?;
+        push(&mut s, self.up_command(&compose, pull, services.as_deref()))?;
+        // A new worktree gets new containers; only existing ones leave anything to decide.
+        let existing = workspace.path.exists();
+        if existing && self.recreate_flag().is_none() {
+            s.push_str("     (compose decides at run time which containers to recreate)\n");
+        }
+
+        let lifecycle = [
+            ("onCreateCommand", &config.on_create_command, true),
+            ("updateContentCommand", &config.update_content_command, true),
+            ("postCreateCommand", &config.post_create_command, true),
+            ("postStartCommand", &config.post_start_command, false),
+        ];
+        for (name, cmd, create_only) in lifecycle {
+            let Some(cmd) = cmd else { continue };
+            let when = if create_only && existing {
+                ", if the primary container is new (decided at run time)"
+            } else {
+                ""
+            };
+            push(
+                &mut s,
+                format!(
+                    "{name}, in the container in {}{when}: {}",
+                    container_dir.display(),
+                    describe(cmd)
+                ),
+            )?;
+        }
+        if self.forward {
+            push(&mut s, "forward `forwardPorts`".to_owned())?;
+        } else if existing && !config.forward_ports.is_empty() {
+            push(
+                &mut s,
+                "forward `forwardPorts` again, if the workspace is forwarding them now (decided at \
+                 run time)"
+                    .to_owned(),
+            )?;
+        }
+        if let Some(exec) = &self.exec {
+            let what = if exec.is_empty() {
+                "the default command".to_owned()
+            } else {
+                exec.join(" ")
+            };
+            push(&mut s, format!("exec {what}"))?;
+        }
+
+        let doc = compose_override(&devcontainer, workspace, self.publish)?;
+        writeln!(
+            s,
+            "\nCompose override:\n{}",
+            serde_json::to_string_pretty(&doc)?
+        )?;
+        print!("{s}");
+        Ok(())
+    }
+
+    /// The `docker compose up` that `up` runs, starting `services` (all of them if `None`).
+    fn up_command(&self, compose: &str, pull: PullPolicy, services: Option<&[String]>) -> String {
+        let mut up = format!("{compose} up -d --remove-orphans --pull {}", pull.as_str());
+        if self.build_arg.is_empty() {
+            up.push_str(" --build");
+        }
+        if let Some(flag) = self.recreate_flag() {
+            up.push_str(&format!(" {flag}"));
+        }
+        for service in services.into_iter().flatten() {
+            up.push_str(&format!(" {service}"));
+        }
+        up
+    }
+}
+
+/// The devcontainer the workspace would get. A worktree that doesn't exist yet has no
+/// devcontainer.json to read, so it's taken from the root, as checked out in the new worktree.
+fn planned_devcontainer(
+    state: &State<'_>,
+    workspace: &Workspace<'_>,
+) -> eyre::Result<DevcontainerState> {
+    if workspace.path.exists() {
+        return state.devcontainer_for(&workspace.path);
+    }
+    let root = state.try_devcontainer()?;
+    let path = root
+        .path
+        .as_deref()
+        .and_then(|p| p.strip_prefix(&state.project.path).ok())
+        .map(|p| workspace.path.join(p));
+    Ok(DevcontainerState {
+        path,
+        config: root.config.clone(),
+        docker: root.docker.clone(),
+    })
+}
+
+fn describe(cmd: &LifecycleCommand) -> String {
+    let one = |cmd: &Cmd| match cmd {
+        Cmd::Shell(script) => script.clone(),
+        Cmd::Args(args) => args.join(" "),
+    };
+    match cmd {
+        LifecycleCommand::Single(cmd) => one(cmd),
+        LifecycleCommand::Parallel(cmds) => cmds
+            .iter()
+            .map(|(name, cmd)| format!("[{name}] {}", one(cmd)))
+            .collect::<Vec<_>>()
+            .join("; "),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use serde_json::json;
+
+    use super::*;
+    use crate::cli::{Cli, Commands};
+    use crate::devcontainer::DevcontainerConfig;
+
+    fn parse(args: &[&str]) -> Up {
+        let args = ["dc", "up"].iter().chain(args);
+        let Commands::Up(up) = Cli::try_parse_from(args).unwrap().command else {
+            panic!("expected up");
+        };
+        up
+    }
+
+    #[test]
+    fn up_command_leaves_recreation_to_compose() {
+        let up = |args: &[&str]| parse(args).up_command("dc", PullPolicy::Missing, None);
+        let base = "dc up -d --remove-orphans --pull missing --build";
+        assert_eq!(up(&["ws"]), base);
+        assert_eq!(
+            up(&["--recreate", "ws"]),
+            format!("{base} --force-recreate")
+        );
+        assert_eq!(
+            up(&["--no-recreate", "ws"]),
+            format!("{base} --no-recreate")
+        );
+    }
+
+    #[test]
+    fn up_after_build_args_lets_compose_recreate() {
+        // Images were just built; compose recreates the containers whose image changed, so
+        // nothing may stop it.
+        let up = parse(&["--build-arg", "RUST=1.90", "ws"]);
+        assert_eq!(
+            up.up_command("dc", PullPolicy::Missing, None),
+            "dc up -d --remove-orphans --pull missing"
+        );
+    }
+
+    #[test]
+    fn up_command_names_dependencies() {
+        let up = parse(&["--recreate", "ws"]);
+        let config: DevcontainerConfig =
+            serde_json::from_value(json!({ "service": "app", "runServices": ["worker"] })).unwrap();
+        let project = json!({ "services": {
+            "app": { "depends_on": { "db": { "condition": "service_healthy" } } },
+            "worker": {},
+            "db": {},
+            "docs": {},
+        } });
+        let services = services_to_start(&config, Some(&project)).unwrap();
+        assert_eq!(
+            up.up_command("docker compose", PullPolicy::Missing, services.as_deref()),
+            "docker compose up -d --remove-orphans --pull missing --build --force-recreate \
+             worker app db"
+        );
+    }
+}
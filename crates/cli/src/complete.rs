@@ -5,17 +5,25 @@ use clap_complete::Shell;
 use clap_complete::engine::CompletionCandidate;
 
 use crate::cli::{Cli, Commands};
-use crate::config::Config;
+use crate::config::{CONFIG_ENV, Config};
 use crate::helpers::SHELL_FD;
 use crate::worktree;
 
+/// The config, as [`Config::load`] finds it, except that stdin is the shell's, not a config.
+fn load_config() -> eyre::Result<Config> {
+    if std::env::var_os(CONFIG_ENV).is_some_and(|p| p == "-") {
+        eyre::bail!("{CONFIG_ENV} is stdin");
+    }
+    Config::load(None)
+}
+
 fn is_completion_candidate(prefix: &str, candidate: &str) -> bool {
     candidate.starts_with(prefix) && candidate != prefix
 }
 
 pub(crate) fn complete_project(current: &OsStr) -> Vec<CompletionCandidate> {
     let prefix = current.to_string_lossy();
-    let Ok(config) = Config::load() else {
+    let Ok(config) = load_config() else {
         return vec![];
     };
 
@@ -33,7 +41,7 @@ pub(crate) fn complete_workspace(current: &OsStr) -> Vec<CompletionCandidate> {
 
 fn complete_workspace_inner(current: &OsStr) -> eyre::Result<Vec<CompletionCandidate>> {
     let prefix = current.to_string_lossy();
-    let config = Config::load()?;
+    let config = load_config()?;
     let (_project_name, project) = config.project(parse_project_arg())?;
 
     let workspaces = worktree::list_sync(&project.path)
@@ -102,41 +110,125 @@ fn compose_prior_args() -> eyre::Result<Vec<String>> {
     Ok(compose.args)
 }
 
+/// Subcommands that may send commands back to the calling shell (see
+/// [`forward_to_shell`](crate::helpers::forward_to_shell)). Add to this list
+/// when a new subcommand needs to affect the shell.
+const SHELL_SUBCOMMANDS: &[&str] = &["go", "up"];
+
 /// Return a shell wrapper function for `dc`.
 ///
-/// The wrapper exposes FD 3 to the binary (advertised via `SHELL_FD`); any
-/// shell command the binary writes there is `eval`ed in the calling shell.
-/// This lets subcommands like `go` cause a `cd` to take effect, without the
-/// wrapper needing to know which subcommands do what.
+/// For [`SHELL_SUBCOMMANDS`], the wrapper exposes FD 3 to the binary
+/// (advertised via `SHELL_FD`); any shell command the binary writes there is
+/// `eval`ed in the calling shell. This lets `go` cause a `cd` to take effect.
+/// Every other invocation runs the binary untouched.
+///
+/// The wrapper matches any argument against the list, rather than parsing out
+/// the subcommand, so e.g. `dc exec go` is captured too; that's harmless, as
+/// capturing is otherwise transparent.
 pub(crate) fn shell_function(shell: Shell, binary: &str) -> eyre::Result<String> {
     let quoted = shlex::try_quote(binary)?;
     let function = match shell {
-        Shell::Bash | Shell::Zsh => format!(
-            r#"
+        Shell::Bash | Shell::Zsh => {
+            let pattern = SHELL_SUBCOMMANDS.join("|");
+            format!(
+                r#"
 dc() {{
-    local cmds rc
-    {{ cmds=$({SHELL_FD}=3 {quoted} "$@" 3>&1 1>&4); rc=$?; }} 4>&1
-    [ -n "$cmds" ] && eval "$cmds"
-    return $rc
+    local arg cmds rc
+    for arg in "$@"; do
+        case "$arg" in
+            {pattern})
+                {{ cmds=$({SHELL_FD}=3 {quoted} "$@" 3>&1 1>&4); rc=$?; }} 4>&1
+                # Don't act on whatever a failed command managed to print.
+                [ $rc -eq 0 ] && [ -n "$cmds" ] && eval "$cmds"
+                return $rc
+                ;;
+        esac
+    done
+    {quoted} "$@"
 }}
 "#
-        ),
-        Shell::Fish => format!(
-            r#"
+            )
+        }
+        Shell::Fish => {
+            let subcommands = SHELL_SUBCOMMANDS.join(" ");
+            format!(
+                r#"
 function dc --wraps {quoted}
-    set -lx {SHELL_FD} 3
-    set -l tmp (mktemp)
-    {quoted} $argv 3>$tmp
-    set -l rc $status
-    set -l cmds (cat $tmp)
-    rm -f $tmp
-    test -n "$cmds"
-    and eval $cmds
-    return $rc
+    for arg in $argv
+        if contains -- $arg {subcommands}
+            set -lx {SHELL_FD} 3
+            set -l tmp (mktemp)
+            {quoted} $argv 3>$tmp
+            set -l rc $status
+            # Source rather than `eval (cat ...)`, which would split multi-line output.
+            test $rc -eq 0
+            and test -s $tmp
+            and source $tmp
+            rm -f $tmp
+            return $rc
+        end
+    end
+    {quoted} $argv
 end
 "#
-        ),
+            )
+        }
         shell => eyre::bail!("unsupported shell {shell}"),
     };
     Ok(function)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+
+    use super::*;
+
+    /// Run `dc {args}` through the bash wrapper around a binary that reports
+    /// whether it was given the shell fd.
+    fn bash_wrapper_fd(args: &str) -> String {
+        let dir = tempfile::tempdir().unwrap();
+        let fake = dir.path().join("dc");
+        let script = format!("#!/bin/sh\necho \"${{{SHELL_FD}:-none}}\"\n");
+        std::fs::write(&fake, script).unwrap();
+        std::fs::set_permissions(&fake, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let wrapper = shell_function(Shell::Bash, &fake.to_string_lossy()).unwrap();
+        let out = std::process::Command::new("bash")
+            .arg("-c")
+            .arg(format!("{wrapper}\ndc {args}"))
+            .output()
+            .unwrap();
+        String::from_utf8(out.stdout).unwrap().trim().to_string()
+    }
+
+    #[test]
+    fn bash_wrapper_captures_listed_subcommands() {
+        assert_eq!(bash_wrapper_fd("go foo"), "3");
+        assert_eq!(bash_wrapper_fd("-p proj up -g foo"), "3");
+    }
+
+    #[test]
+    fn bash_wrapper_passes_others_through() {
+        assert_eq!(bash_wrapper_fd("status"), "none");
+    }
+
+    #[test]
+    fn bash_wrapper_skips_commands_of_failed_go() {
+        let dir = tempfile::tempdir().unwrap();
+        let fake = dir.path().join("dc");
+        let script = format!("#!/bin/sh\necho 'cd /' >&${SHELL_FD}\nexit 3\n");
+        std::fs::write(&fake, script).unwrap();
+        std::fs::set_permissions(&fake, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let wrapper = shell_function(Shell::Bash, &fake.to_string_lossy()).unwrap();
+        let out = std::process::Command::new("bash")
+            .arg("-c")
+            .arg(format!("{wrapper}\ndc go foo; echo \"$? $PWD\""))
+            .current_dir(dir.path())
+            .output()
+            .unwrap();
+        let out = String::from_utf8(out.stdout).unwrap();
+        assert_eq!(out.trim(), format!("3 {}", dir.path().display()));
+    }
+}
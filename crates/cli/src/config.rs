@@ -1,15 +1,32 @@
+use std::ffi::OsString;
 use std::path::{Path, PathBuf};
 
+use color_eyre::Section;
 use eyre::{WrapErr, eyre};
 use indexmap::IndexMap;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use serde_inline_default::serde_inline_default;
 
 use crate::devcontainer::DevcontainerConfig;
 use crate::helpers::{deserialize_shell_path, deserialize_shell_path_opt, validate_name};
+use crate::run::Runner;
+use crate::run::cmd::NamedCmd;
+use crate::{paths, preflight};
 
 pub(crate) const DEFAULT_PROXY_PORT: u16 = 43770;
 
+/// Project settings checked into a repository, relative to its root. Lets a repository be used
+/// without configuring it in `config.toml`.
+const LOCAL_CONFIG: &str = ".devcontainer/dc.toml";
+
+/// Where to read `config.toml` from instead of the usual place; `-` for stdin.
+pub(crate) const CONFIG_ENV: &str = "DC_CONFIG";
+/// The path of a project given by the environment, when there's no config file.
+const PROJECT_PATH_ENV: &str = "DC_PROJECT_PATH";
+/// Where that project's worktrees go.
+const WORKSPACE_DIR_ENV: &str = "DC_WORKSPACE_DIR";
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub(crate) struct ProjectName(String);
 
@@ -58,12 +75,31 @@ impl<'de> Deserialize<'de> for ProjectName {
     }
 }
 
-#[derive(Debug, Deserialize, JsonSchema)]
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
 pub(crate) struct Config {
     #[serde(default)]
     pub(crate) projects: IndexMap<ProjectName, Project>,
     #[serde(default)]
     pub(crate) proxy: ProxyGlobal,
+    /// Skip the confirmation prompt for bulk operations affecting at most this
+    /// many workspaces, as long as none of them are dirty or in use. Leave unset
+    /// to always prompt.
+    #[serde(default)]
+    pub(crate) confirm_threshold: Option<usize>,
+    /// Keep this configuration's containers apart from those of any other on the same Docker
+    /// daemon. Everything devconcurrent creates is labeled with it, and only containers with a
+    /// matching label are considered. Leave unset for the default namespace.
+    #[serde(default)]
+    pub(crate) label_namespace: Option<String>,
+}
+
+impl Config {
+    /// Value of [`docker::MANAGED_LABEL`] for containers we create; the default namespace matches
+    /// what devconcurrent has always written.
+    pub(crate) fn managed_label_value(&self) -> &str {
+        self.label_namespace.as_deref().unwrap_or("true")
+    }
 }
 
 /// Global user proxy settings.
@@ -90,6 +126,7 @@ impl Default for ProxyGlobal {
     }
 }
 
+#[serde_inline_default]
 #[derive(Debug, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub(crate) struct Project {
@@ -100,24 +137,261 @@ pub(crate) struct Project {
     // We'll parse this properly when merging with Figment.
     #[schemars(with = "Option<DevcontainerConfig>")]
     pub(crate) devcontainer: Option<toml::Value>,
+    /// The devcontainer.json to use, relative to the repository root, e.g.
+    /// `"apps/web/.devcontainer/devcontainer.json"`. By default, it's found in the usual places at
+    /// the root.
+    #[serde(default)]
+    pub(crate) devcontainer_path: Option<PathBuf>,
+    /// Which changes count towards a workspace being dirty.
+    ///
+    /// Default: "tracked"
+    #[serde(default)]
+    pub(crate) dirty_check: DirtyCheck,
+    /// Template for the names of new workspaces, e.g. `"{user}-{name}"`. Supports `{name}`
+    /// (required), `{project}`, `{date}` (`YYYY-MM-DD`), and `{user}`.
+    #[serde(default)]
+    pub(crate) name_template: Option<String>,
+    /// Write `.dc/workspace.json`, recording where the workspace came from, into new worktrees.
+    ///
+    /// Default: true
+    #[serde_inline_default(true)]
+    pub(crate) workspace_metadata: bool,
+    /// A git URL to clone into `path` the first time the project is used, if nothing is there
+    /// yet.
+    #[serde(default)]
+    pub(crate) repo: Option<String>,
 }
 
-impl Config {
-    pub(crate) fn load() -> eyre::Result<Self> {
-        let dirs = directories::ProjectDirs::from("", "", "devconcurrent")
-            .ok_or_else(|| eyre::eyre!("could not determine config directory"))?;
-        let path = dirs.config_dir().join("config.toml");
-        Self::load_from_path(&path)
+/// How to decide whether a workspace has uncommitted changes.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum DirtyCheck {
+    /// Any change, including untracked files.
+    All,
+    /// Only changes to tracked files; untracked files (build artifacts,
+    /// caches) are ignored.
+    #[default]
+    Tracked,
+    /// Only changes matching these git pathspecs, e.g. `[":!target"]`.
+    Pathspec(Vec<String>),
+}
+
+impl Project {
+    /// A project at `path` with every other setting at its default.
+    fn at(path: PathBuf) -> Self {
+        Self {
+            path,
+            worktree_folder: None,
+            devcontainer: None,
+            devcontainer_path: None,
+            dirty_check: DirtyCheck::default(),
+            name_template: None,
+            workspace_metadata: true,
+            repo: None,
+        }
     }
 
-    pub(crate) fn load_from_path(path: &Path) -> eyre::Result<Self> {
+    /// The devcontainer.json for the worktree at `dir`: [`Project::devcontainer_path`] if it's
+    /// set, erroring if it's missing, else whichever [`DevcontainerConfig::find_config`] finds.
+    pub(crate) fn find_devcontainer(&self, dir: &Path) -> eyre::Result<Option<PathBuf>> {
+        let Some(path) = &self.devcontainer_path else {
+            return Ok(DevcontainerConfig::find_config(dir));
+        };
+        let path = dir.join(path);
+        if !path.is_file() {
+            return Err(eyre!("no devcontainer.json at {}", path.display()))
+                .suggestion("check `devcontainerPath`, which is relative to the repository root");
+        }
+        Ok(Some(path))
+    }
+
+    /// Clone [`Project::repo`] into `path` if there's nothing there yet.
+    pub(crate) async fn ensure_cloned(&self) -> eyre::Result<()> {
+        let Some(repo) = &self.repo else {
+            return Ok(());
+        };
+        if self.path.exists() {
+            return Ok(());
+        }
+        preflight::check_git()?;
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)
+                .wrap_err_with(|| format!("failed to create {}", parent.display()))?;
+        }
+
+        let mut clone = std::process::Command::new("git");
+        clone.arg("clone").arg(repo).arg(&self.path);
+        let cmd = (&clone).into();
+        Runner::run(NamedCmd {
+            name: "git clone",
+            cmd: &cmd,
+            dir: None,
+        })
+        .await
+    }
+
+    /// Load a repository's [`LOCAL_CONFIG`], which holds everything a `projects.FOO` entry
+    /// would except `path`.
+    fn load_local(repo_root: &Path, path: &Path) -> eyre::Result<Self> {
         let contents = std::fs::read_to_string(path)
             .wrap_err_with(|| format!("failed to load {}", path.display()))?;
-        let de = toml::Deserializer::parse(&contents)
+        let mut table: toml::Table = toml::from_str(&contents)
             .wrap_err_with(|| format!("failed to parse {}", path.display()))?;
-        serde_path_to_error::deserialize(de)
+        table.insert("path".into(), repo_root.display().to_string().into());
+        toml::Value::Table(table)
+            .try_into()
             .wrap_err_with(|| format!("failed to parse {}", path.display()))
     }
+}
+
+impl Config {
+    /// Load `config.toml` from `path`, else [`CONFIG_ENV`], else the config directory, where it
+    /// may be missing. A path of `-` reads it from stdin instead.
+    pub(crate) fn load(path: Option<&Path>) -> eyre::Result<Self> {
+        let given = path.map(Path::to_owned).or_else(|| {
+            std::env::var_os(CONFIG_ENV)
+                .filter(|p| !p.is_empty())
+                .map(PathBuf::from)
+        });
+        let mut config = match given {
+            Some(path) if path == Path::new("-") => {
+                let contents =
+                    std::io::read_to_string(std::io::stdin()).wrap_err("failed to read stdin")?;
+                Self::parse(&contents, "config from stdin")?
+            }
+            Some(path) => Self::load_from_path(&path)?,
+            None => {
+                let path = paths::config_dir()?.join("config.toml");
+                if path.exists() {
+                    Self::load_from_path(&path)?
+                } else {
+                    Self::from_env(|var| std::env::var_os(var))?
+                }
+            }
+        };
+        config.add_implicit_project()?;
+        Ok(config)
+    }
+
+    /// Without a config file, a single project can be given by [`PROJECT_PATH_ENV`] and
+    /// [`WORKSPACE_DIR_ENV`]. It's named by `DC_PROJECT`, or else after its directory.
+    fn from_env(var: impl Fn(&str) -> Option<OsString>) -> eyre::Result<Self> {
+        let mut config = Self::default();
+        let Some(path) = var(PROJECT_PATH_ENV).filter(|p| !p.is_empty()) else {
+            return Ok(config);
+        };
+        let path = PathBuf::from(path);
+        let name = match var("DC_PROJECT") {
+            Some(name) => name,
+            None => path
+                .file_name()
+                .ok_or_else(|| eyre!("can't name a project after {}", path.display()))?
+                .to_owned(),
+        };
+        let name = ProjectName::new(name.to_string_lossy().into_owned())
+            .map_err(|e| eyre!("invalid project name: {e}"))?;
+
+        let mut project = Project::at(path);
+        project.worktree_folder = var(WORKSPACE_DIR_ENV).map(PathBuf::from);
+        config.projects.insert(name, project);
+        Ok(config)
+    }
+
+    /// Treat the repository containing the cwd as a project, named after its directory, if it
+    /// isn't configured but has a [`LOCAL_CONFIG`] or devcontainer.json of its own.
+    fn add_implicit_project(&mut self) -> eyre::Result<()> {
+        let Some(root) = std::env::current_dir()
+            .ok()
+            .and_then(|cwd| repo_root_for(&cwd))
+        else {
+            return Ok(());
+        };
+        if self.project_name_for_repo_root(&root)?.is_some() {
+            return Ok(());
+        }
+        let Some(name) = root
+            .file_name()
+            .and_then(|n| ProjectName::new(n.to_string_lossy().into_owned()).ok())
+        else {
+            return Ok(());
+        };
+        // Don't shadow a configured project that happens to share the name.
+        if self.projects.contains_key(&name) {
+            return Ok(());
+        }
+
+        let local = root.join(LOCAL_CONFIG);
+        let project = if local.is_file() {
+            Project::load_local(&root, &local)?
+        } else if DevcontainerConfig::find_config(&root).is_some() {
+            Project::at(root)
+        } else {
+            return Ok(());
+        };
+        self.projects.insert(name, project);
+        Ok(())
+    }
+
+    pub(crate) fn load_from_path(path: &Path) -> eyre::Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .wrap_err_with(|| format!("failed to load {}", path.display()))?;
+        Self::parse(&contents, &path.display().to_string())
+    }
+
+    /// Parse `config.toml` contents; `source` names where they came from, for errors.
+    fn parse(contents: &str, source: &str) -> eyre::Result<Self> {
+        let de = toml::Deserializer::parse(contents)
+            .wrap_err_with(|| format!("failed to parse {source}"))?;
+        serde_path_to_error::deserialize(de).wrap_err_with(|| format!("failed to parse {source}"))
+    }
+
+    /// Resolve a `--project` argument that's a path (`.`, `../web`) rather than a configured name
+    /// to the project of the repository there, adding one rooted there if none is configured.
+    /// Other arguments are returned as they are.
+    pub(crate) fn resolve_project_arg(
+        &mut self,
+        arg: Option<String>,
+    ) -> eyre::Result<Option<String>> {
+        let Some(arg) = arg else {
+            return Ok(None);
+        };
+        let configured =
+            ProjectName::new(arg.clone()).is_ok_and(|n| self.projects.contains_key(&n));
+        if configured || !(arg.contains('/') || Path::new(&arg).exists()) {
+            return Ok(Some(arg));
+        }
+
+        let path = Path::new(&arg)
+            .canonicalize()
+            .wrap_err_with(|| format!("no project or directory named {arg:?}"))?;
+        let root = repo_root_for(&path)
+            .ok_or_else(|| eyre!("{} is not in a git repository", path.display()))?;
+        if let Some(name) = self.project_name_for_repo_root(&root)? {
+            return Ok(Some(name.to_string()));
+        }
+
+        let name = root
+            .file_name()
+            .and_then(|n| ProjectName::new(n.to_string_lossy().into_owned()).ok())
+            .ok_or_else(|| eyre!("can't name a project after {}", root.display()))?;
+        if self.projects.contains_key(&name) {
+            return Err(eyre!(
+                "project {name:?} is already configured with a different path"
+            ))
+            .suggestion(format!(
+                "add {} to config.toml under another name",
+                root.display()
+            ));
+        }
+        let local = root.join(LOCAL_CONFIG);
+        let project = if local.is_file() {
+            Project::load_local(&root, &local)?
+        } else {
+            Project::at(root)
+        };
+        self.projects.insert(name.clone(), project);
+        Ok(Some(name.to_string()))
+    }
 
     pub(crate) fn project(
         &self,
@@ -178,6 +452,103 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn project_from_env() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |var: &str| {
+                vars.iter()
+                    .find(|(k, _)| *k == var)
+                    .map(|(_, v)| OsString::from(v))
+            }
+        };
+
+        let config = Config::from_env(env(&[])).unwrap();
+        assert!(config.projects.is_empty());
+
+        let config = Config::from_env(env(&[
+            ("DC_PROJECT_PATH", "/ci/checkout/web"),
+            ("DC_WORKSPACE_DIR", "/ci/workspaces"),
+        ]))
+        .unwrap();
+        let (name, project) = config.projects.first().unwrap();
+        assert_eq!(name.as_str(), "web");
+        assert_eq!(project.path, Path::new("/ci/checkout/web"));
+        assert_eq!(
+            project.worktree_folder.as_deref(),
+            Some(Path::new("/ci/workspaces"))
+        );
+
+        let config = Config::from_env(env(&[
+            ("DC_PROJECT_PATH", "/ci/checkout"),
+            ("DC_PROJECT", "app"),
+        ]))
+        .unwrap();
+        assert_eq!(config.projects.first().unwrap().0.as_str(), "app");
+    }
+
+    #[test]
+    fn devcontainer_path_is_relative_to_the_worktree() {
+        let dir = tempfile::tempdir().unwrap();
+        let web = dir.path().join("apps/web/.devcontainer");
+        std::fs::create_dir_all(&web).unwrap();
+        std::fs::write(web.join("devcontainer.json"), "{}").unwrap();
+
+        let mut project = Project::at(dir.path().to_owned());
+        assert_eq!(project.find_devcontainer(dir.path()).unwrap(), None);
+
+        project.devcontainer_path = Some("apps/web/.devcontainer/devcontainer.json".into());
+        assert_eq!(
+            project.find_devcontainer(dir.path()).unwrap(),
+            Some(web.join("devcontainer.json"))
+        );
+
+        project.devcontainer_path = Some("apps/api/devcontainer.json".into());
+        assert!(project.find_devcontainer(dir.path()).is_err());
+    }
+
+    #[test]
+    fn project_arg_can_be_a_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("web");
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        let status = std::process::Command::new("git")
+            .arg("init")
+            .arg("-q")
+            .arg(&root)
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let mut config = Config::default();
+        let arg = root.join("src").display().to_string();
+        let name = config.resolve_project_arg(Some(arg.clone())).unwrap();
+        assert_eq!(name.as_deref(), Some("web"));
+        assert_eq!(config.projects.len(), 1);
+        // Found again, not added twice.
+        assert_eq!(config.resolve_project_arg(Some(arg)).unwrap(), name);
+        assert_eq!(config.projects.len(), 1);
+
+        assert_eq!(
+            config.resolve_project_arg(Some("web".into())).unwrap(),
+            name
+        );
+        assert_eq!(
+            config
+                .resolve_project_arg(Some("elsewhere".into()))
+                .unwrap(),
+            Some("elsewhere".into())
+        );
+    }
+
+    #[test]
+    fn parse_names_its_source_in_errors() {
+        let config = Config::parse("[projects.app]\npath = \"/code/app\"\n", "stdin").unwrap();
+        assert_eq!(config.projects.first().unwrap().0.as_str(), "app");
+
+        let err = Config::parse("[projects.app]\n", "config from stdin").unwrap_err();
+        assert_eq!(err.to_string(), "failed to parse config from stdin");
+    }
+
     #[test]
     fn project_order_is_stable() {
         let names = [
@@ -201,4 +572,37 @@ mod tests {
             assert_eq!(got, expected, "project order changed on iteration {i}");
         }
     }
+
+    #[test]
+    fn dirty_check_modes() {
+        let parse = |extra: &str| {
+            let toml = format!("[projects.foo]\npath = \"/tmp/foo\"\n{extra}");
+            let mut file = tempfile::Builder::new().suffix(".toml").tempfile().unwrap();
+            file.write_all(toml.as_bytes()).unwrap();
+            let cfg = Config::load_from_path(file.path()).unwrap();
+            cfg.projects.into_values().next().unwrap().dirty_check
+        };
+
+        assert_eq!(parse(""), DirtyCheck::Tracked);
+        assert_eq!(parse("dirtyCheck = \"all\""), DirtyCheck::All);
+        assert_eq!(
+            parse("dirtyCheck = { pathspec = [\":!target\"] }"),
+            DirtyCheck::Pathspec(vec![":!target".into()]),
+        );
+    }
+
+    #[test]
+    fn local_config_uses_repo_root() {
+        let mut file = tempfile::Builder::new().suffix(".toml").tempfile().unwrap();
+        file.write_all(b"worktreeFolder = \"../worktrees\"\ndirtyCheck = \"all\"\n")
+            .unwrap();
+
+        let project = Project::load_local(Path::new("/repo"), file.path()).unwrap();
+        assert_eq!(project.path, Path::new("/repo"));
+        assert_eq!(
+            project.worktree_folder.as_deref(),
+            Some(Path::new("../worktrees"))
+        );
+        assert_eq!(project.dirty_check, DirtyCheck::All);
+    }
 }
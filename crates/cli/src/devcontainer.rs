@@ -1,5 +1,6 @@
 use std::path::{Path, PathBuf};
 
+use color_eyre::Section;
 use eyre::WrapErr;
 use figment::{
     Figment,
@@ -9,17 +10,25 @@ use indexmap::IndexMap;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_inline_default::serde_inline_default;
+use serde_with::formats::PreferMany;
 use serde_with::{OneOrMany, serde_as};
 
+pub(crate) mod app_port;
 pub(crate) mod dc_options;
 pub(crate) mod forward_port;
 pub(crate) mod lifecycle_command;
+mod port;
+pub(crate) mod run_args;
 pub(crate) mod substitution;
 mod unsupported;
 
 use crate::{
     config::Project,
-    devcontainer::{dc_options::DcOptions, forward_port::ForwardPort, substitution::Template},
+    devcontainer::{
+        app_port::AppPort, dc_options::DcOptions, forward_port::ForwardPort, substitution::Template,
+    },
+    helpers::read_env_file,
+    redact,
 };
 use lifecycle_command::LifecycleCommand;
 use unsupported::Unsupported;
@@ -32,7 +41,7 @@ pub(crate) struct DevcontainerConfig {
     // -------------------------------------------------------------------------
     // Compose section
     /// The name of the docker-compose file(s) used to start the services.
-    #[serde_as(as = "OneOrMany<_>")]
+    #[serde_as(as = "OneOrMany<_, PreferMany>")]
     pub(crate) docker_compose_file: Vec<String>,
     /// The service you want to work on. This is considered the primary container for your dev
     /// environment which your editor will connect to.
@@ -42,6 +51,8 @@ pub(crate) struct DevcontainerConfig {
     pub(crate) run_services: Option<Vec<String>>,
     /// The path of the workspace folder inside the container. This is typically the target path of
     /// a volume mount in the docker-compose.yml.
+    ///
+    /// Defaults to `/workspaces/<project folder name>`, as the devcontainer CLI does.
     pub(crate) workspace_folder: PathBuf,
     /// Action to take when the user disconnects from the primary container in their editor. The
     /// default is to stop all of the compose containers.
@@ -50,6 +61,34 @@ pub(crate) struct DevcontainerConfig {
     /// Whether to overwrite the command specified in the image. The default is false.
     #[serde(default)]
     pub(crate) override_command: bool,
+    /// Ports to publish from the primary container, as `port` or `"host:container"`.
+    ///
+    /// The reference only honors this for image and Dockerfile configs; we publish them through
+    /// the compose override. Each workspace binds the same host ports, so prefer `forwardPorts`
+    /// when running more than one at a time.
+    #[serde_as(as = "OneOrMany<_, PreferMany>")]
+    pub(crate) app_port: Vec<AppPort>,
+    /// Extra `docker run` arguments for the primary container.
+    ///
+    /// Common flags (`--cap-add`, `--device`, `--network`, ...) are translated into the compose
+    /// override; any others are ignored with a warning.
+    pub(crate) run_args: Vec<String>,
+    // -------------------------------------------------------------------------
+    // Image and Dockerfile section
+    /// The docker image to use for the container, when not using `dockerComposeFile`.
+    ///
+    /// We run these through compose too, with a generated compose file holding a single service.
+    pub(crate) image: Option<String>,
+    /// Docker build options, for building the container from a Dockerfile.
+    pub(crate) build: Option<BuildOptions>,
+    /// The location of the Dockerfile, relative to devcontainer.json. Deprecated in favor of
+    /// `build.dockerfile`.
+    pub(crate) docker_file: Option<String>,
+    /// The build context, relative to devcontainer.json. Deprecated in favor of `build.context`.
+    pub(crate) context: Option<String>,
+    /// The mount of the workspace in the container, in docker `--mount` form. Defaults to a bind
+    /// of the workspace at `workspaceFolder`.
+    pub(crate) workspace_mount: Option<Template>,
     // -------------------------------------------------------------------------
     // Common section
     /// The JSON schema of the devcontainer.json file.
@@ -64,13 +103,16 @@ pub(crate) struct DevcontainerConfig {
     /// the user wants them to be installed.
     #[serde(deserialize_with = "unsupported::overrideFeatureInstallOrder::warn")]
     pub(crate) override_feature_install_order: Vec<String>,
-    #[serde(deserialize_with = "unsupported::secrets::warn")]
-    pub(crate) secrets: serde_json::Value,
+    /// Recommended secrets for this dev container, keyed by environment variable name.
+    ///
+    /// Values are read from the host environment, then from
+    /// `customizations.devconcurrent.secretsFile`, and passed to lifecycle commands and `dc exec`
+    /// without being written into the compose override or container labels.
+    pub(crate) secrets: IndexMap<String, Secret>,
     pub(crate) forward_ports: Vec<ForwardPort>,
     pub(crate) ports_attributes: IndexMap<String, PortAttributes>,
-    /// Set default properties that are applied to all ports that don't get properties from the
-    /// setting `remote.portsAttributes`
-    #[serde(deserialize_with = "unsupported::otherPortsAttributes::warn")]
+    /// Set default properties that are applied to all ports that don't get properties from
+    /// `portsAttributes`
     pub(crate) other_ports_attributes: Option<PortAttributes>,
     /// Controls whether on Linux the container's user should be updated with the local user's UID
     /// and GID. On by default when opening from a local folder.
@@ -129,7 +171,77 @@ pub(crate) struct DevcontainerConfig {
     pub(crate) customizations: Customizations,
 }
 
+/// Docker build options for a Dockerfile devcontainer.
+#[derive(Deserialize, Serialize, Clone, Debug, Default, JsonSchema)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct BuildOptions {
+    /// The location of the Dockerfile, relative to devcontainer.json.
+    pub(crate) dockerfile: Option<String>,
+    /// The build context, relative to devcontainer.json. Defaults to its directory.
+    pub(crate) context: Option<String>,
+    /// The target stage of a multi-stage build.
+    pub(crate) target: Option<String>,
+    /// Build arguments.
+    pub(crate) args: IndexMap<String, String>,
+}
+
+/// The uncommitted override for the devcontainer.json at `path`: `devcontainer.override.json` next
+/// to it.
+fn override_path(path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let stem = name.strip_suffix(".json").unwrap_or(&name);
+    path.with_file_name(format!("{stem}.override.json"))
+}
+
+/// The `workspaceFolder` to use when none is configured: `/workspaces/<project folder name>`.
+fn default_workspace_folder(project_root: &Path) -> eyre::Result<PathBuf> {
+    let name = project_root.file_name().ok_or_else(|| {
+        eyre::eyre!(
+            "can't infer `workspaceFolder` from {}",
+            project_root.display()
+        )
+    });
+    let name = name.suggestion("set `workspaceFolder` in devcontainer.json")?;
+    Ok(Path::new("/workspaces").join(name))
+}
+
+/// The compose service name for an image or Dockerfile devcontainer: its `name`, cut down to
+/// what compose accepts.
+fn single_container_service(name: Option<&str>) -> String {
+    let service: String = name
+        .unwrap_or_default()
+        .to_lowercase()
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'))
+        .collect();
+    if service.is_empty() {
+        "devcontainer".to_owned()
+    } else {
+        service
+    }
+}
+
 impl DevcontainerConfig {
+    /// Whether this is an image or Dockerfile devcontainer, rather than a compose one.
+    pub(crate) fn is_single_container(&self) -> bool {
+        self.docker_compose_file.is_empty()
+            && (self.image.is_some() || self.build.is_some() || self.docker_file.is_some())
+    }
+
+    /// The Dockerfile to build from and its context, relative to devcontainer.json, from `build`
+    /// or the deprecated top-level `dockerFile` and `context`.
+    pub(crate) fn dockerfile(&self) -> Option<(&str, &str)> {
+        let build = self.build.as_ref();
+        let dockerfile = build
+            .and_then(|b| b.dockerfile.as_deref())
+            .or(self.docker_file.as_deref())?;
+        let context = build
+            .and_then(|b| b.context.as_deref())
+            .or(self.context.as_deref())
+            .unwrap_or(".");
+        Some((dockerfile, context))
+    }
+
     /// Find the appropriate devcontainer.json file from the given root directory.
     ///
     /// Return None if there is no devcontainer.json file, and treat the project as one that
@@ -170,8 +282,8 @@ impl DevcontainerConfig {
         })
     }
 
-    /// Load the merged devcontainer config from the given path (if any) and the project's
-    /// overrides. Returns `Ok(None)` if neither source provides any config.
+    /// Load the merged devcontainer config from the given path (if any), its
+    /// [`override_path`], and the project's overrides. Returns `Ok(None)` if neither source provides any config.
     pub(crate) fn load(path: Option<&Path>, project: &Project) -> eyre::Result<Option<Self>> {
         if path.is_none() && project.devcontainer.is_none() {
             return Ok(None);
@@ -181,19 +293,110 @@ impl DevcontainerConfig {
 
         if let Some(path) = path {
             figment = figment.admerge(Json::file(path));
+            // Personal tweaks: objects merge, everything else (arrays included) is replaced.
+            let personal = override_path(path);
+            if personal.is_file() {
+                figment = figment.merge(Json::file(personal));
+            }
         }
 
         if let Some(overrides) = &project.devcontainer {
             figment = figment.admerge(Serialized::defaults(overrides));
         }
 
-        let config: Self = figment
+        let mut config: Self = figment
             .extract()
             .wrap_err("failed to merge devcontainer config")?;
+        if config.workspace_folder.as_os_str().is_empty() {
+            config.workspace_folder = default_workspace_folder(&project.path)?;
+        }
+        if config.is_single_container() {
+            if config.service.is_empty() {
+                config.service = single_container_service(config.name.as_deref());
+            }
+            // The reference overrides the image's command for these, unless told not to.
+            if figment.find_value("overrideCommand").is_err() {
+                config.override_command = true;
+            }
+        }
         config.check_proxy_port_conflicts()?;
         Ok(Some(config))
     }
 
+    /// Resolve values for the configured `secrets`.
+    ///
+    /// Host environment variables take precedence over the secrets file; secrets with no value
+    /// anywhere are skipped with a warning. Resolved values are masked in our log output.
+    pub(crate) fn secret_env(&self, project_root: &Path) -> eyre::Result<IndexMap<String, String>> {
+        if self.secrets.is_empty() {
+            return Ok(IndexMap::new());
+        }
+
+        let from_file: IndexMap<String, String> =
+            match &self.customizations.devconcurrent.secrets_file {
+                Some(file) => read_env_file(&project_root.join(file))?
+                    .into_iter()
+                    .collect(),
+                None => IndexMap::new(),
+            };
+
+        let mut env = IndexMap::new();
+        for name in self.secrets.keys() {
+            match std::env::var(name)
+                .ok()
+                .or_else(|| from_file.get(name).cloned())
+            {
+                Some(value) => {
+                    redact::register(&value);
+                    env.insert(name.clone(), value);
+                }
+                None => tracing::warn!("secret `{name}` is not set; skipping"),
+            }
+        }
+        Ok(env)
+    }
+
+    /// The environment to set on execs in the container, over what they inherit from it, which
+    /// includes `containerEnv`: the `userEnvProbe` result, then `remoteEnv`, which wins. A `None`
+    /// (spec `null`) is set to empty, since `docker exec` can't unset a variable.
+    ///
+    /// Probed variables the container already has are left to be inherited, so `containerEnv`
+    /// only gets shadowed by what the user's shell or `remoteEnv` actually changed.
+    pub(crate) fn exec_env(
+        &self,
+        probed: IndexMap<String, String>,
+        context: &substitution::Context<'_>,
+    ) -> IndexMap<String, Option<String>> {
+        let inherited = context.container_env();
+        let mut env: IndexMap<String, Option<String>> = probed
+            .into_iter()
+            .filter(|(k, v)| inherited.and_then(|env| env.get(k)) != Some(v))
+            .map(|(k, v)| (k, Some(v)))
+            .collect();
+        for (key, template) in &self.remote_env {
+            env.insert(key.clone(), template.as_ref().map(|t| t.render(context)));
+        }
+        env
+    }
+
+    /// The attributes for `port`: from the first `portsAttributes` key naming it, either as a port
+    /// or a `start-end` range, then `otherPortsAttributes`, then the defaults.
+    pub(crate) fn port_attributes(&self, port: u16) -> PortAttributes {
+        let matches = |key: &str| match key.split_once('-') {
+            Some((start, end)) => {
+                matches!((start.parse(), end.parse()), (Ok(start), Ok(end)) if (start..=end).contains(&port))
+            }
+            None => key.parse() == Ok(port),
+        };
+        self.ports_attributes
+            .iter()
+            .find(|(key, _)| matches(key))
+            .map(|(_, attrs)| attrs)
+            .or(self.other_ports_attributes.as_ref())
+            .cloned()
+            .unwrap_or_default()
+    }
+
     fn check_proxy_port_conflicts(&self) -> eyre::Result<()> {
         use std::collections::HashMap;
         use std::net::IpAddr;
@@ -213,6 +416,16 @@ impl DevcontainerConfig {
     }
 }
 
+/// Metadata for a recommended secret.
+#[derive(Deserialize, Serialize, Clone, Debug, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Secret {
+    /// A description of the secret.
+    pub(crate) description: Option<String>,
+    /// A URL to documentation about the secret.
+    pub(crate) documentation_url: Option<String>,
+}
+
 #[derive(Deserialize, Serialize, Clone, Debug, Default, JsonSchema)]
 pub(crate) struct Customizations {
     #[serde(default)]
@@ -373,6 +586,18 @@ pub(crate) struct PortAttributes {
     pub(crate) require_local_port: bool,
 }
 
+impl Default for PortAttributes {
+    fn default() -> Self {
+        Self {
+            on_auto_forward: OnAutoForward::default(),
+            elevate_if_needed: false,
+            label: String::from("Application"),
+            protocol: Protocol::default(),
+            require_local_port: false,
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq, Default, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub(crate) enum Protocol {
@@ -432,6 +657,16 @@ mod tests {
         substitution::Context::new(Path::new("/local"), Path::new("/container"))
     }
 
+    /// Load a config given as the project's TOML `devcontainer` overrides, with no
+    /// devcontainer.json.
+    fn load_toml(src: &str) -> DevcontainerConfig {
+        let project = Project {
+            devcontainer: Some(toml::from_str(src).unwrap()),
+            ..toml::from_str(r#"path = "/code/repo""#).unwrap()
+        };
+        DevcontainerConfig::load(None, &project).unwrap().unwrap()
+    }
+
     #[test]
     fn mount_string_bind() {
         let entry: MountEntry =
@@ -499,4 +734,150 @@ mod tests {
         let entry: MountEntry = serde_json::from_str(r#""type=bind,source=/host""#).unwrap();
         assert!(entry.to_compose_volume(&ctx()).is_err());
     }
+
+    #[test]
+    fn override_file_merges_objects_and_replaces_arrays() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("devcontainer.json");
+        std::fs::write(
+            &path,
+            r#"{
+                "dockerComposeFile": "compose.yml",
+                "service": "app",
+                "runServices": ["app", "db"],
+                "remoteEnv": { "A": "base", "B": "base" }
+            }"#,
+        )
+        .unwrap();
+        std::fs::write(
+            override_path(&path),
+            r#"{ "runServices": ["app"], "remoteEnv": { "B": "mine" } }"#,
+        )
+        .unwrap();
+        let project: Project = toml::from_str(r#"path = "/repo""#).unwrap();
+
+        let config = DevcontainerConfig::load(Some(&path), &project)
+            .unwrap()
+            .unwrap();
+        assert_eq!(config.run_services.unwrap(), ["app"]);
+        let env: Vec<_> = config
+            .remote_env
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_ref().unwrap().render(&ctx())))
+            .collect();
+        assert_eq!(env, [("A", "base".to_owned()), ("B", "mine".to_owned())]);
+    }
+
+    #[test]
+    fn port_attributes_fall_back() {
+        let config = load_toml(
+            r#"
+            service = "app"
+            portsAttributes."3000" = { label = "Web" }
+            portsAttributes."5000-5010" = { label = "Workers", protocol = "https" }
+            otherPortsAttributes = { label = "Other" }
+            "#,
+        );
+
+        assert_eq!(config.port_attributes(3000).label, "Web");
+        let workers = config.port_attributes(5005);
+        assert_eq!(workers.label, "Workers");
+        assert_eq!(workers.protocol, Protocol::Https);
+        assert_eq!(config.port_attributes(8080).label, "Other");
+    }
+
+    #[test]
+    fn exec_env_extends_container_path() {
+        let config = load_toml(
+            r#"
+            service = "app"
+            remoteEnv = { PATH = "${containerEnv:PATH}:/extra", HOME = "${localEnv:HOME}" }
+            "#,
+        );
+
+        let container_env = [("PATH", "/usr/bin"), ("HOME", "/home/container")]
+            .into_iter()
+            .map(|(k, v)| (k.to_owned(), v.to_owned()))
+            .collect();
+        let context = ctx().with_container(crate::docker::probe::ContainerData {
+            env: container_env,
+            labels: IndexMap::new(),
+        });
+
+        let env = config.exec_env(IndexMap::new(), &context);
+        assert_eq!(env["PATH"].as_deref(), Some("/usr/bin:/extra"));
+        // `localEnv` stays the host's.
+        assert_eq!(
+            env["HOME"].as_deref(),
+            Some(std::env::var("HOME").unwrap_or_default().as_str())
+        );
+    }
+
+    #[test]
+    fn exec_env_layers_remote_env_over_container_env() {
+        let config = load_toml(
+            r#"
+            service = "app"
+            containerEnv = { ONLY_CONTAINER = "c", BOTH = "c" }
+            remoteEnv = { BOTH = "remote" }
+            "#,
+        );
+
+        let container_env: IndexMap<String, String> =
+            [("ONLY_CONTAINER", "c"), ("BOTH", "c"), ("PATH", "/usr/bin")]
+                .into_iter()
+                .map(|(k, v)| (k.to_owned(), v.to_owned()))
+                .collect();
+        let probed = [
+            ("ONLY_CONTAINER", "c"),
+            ("BOTH", "c"),
+            ("PATH", "/home/me/.cargo/bin:/usr/bin"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_owned(), v.to_owned()))
+        .collect();
+        let context = ctx().with_container(crate::docker::probe::ContainerData {
+            env: container_env,
+            labels: IndexMap::new(),
+        });
+
+        let env = config.exec_env(probed, &context);
+        // ONLY_CONTAINER is inherited from the container, not passed.
+        assert_eq!(env.get("ONLY_CONTAINER"), None);
+        assert_eq!(env["BOTH"].as_deref(), Some("remote"));
+        assert_eq!(env["PATH"].as_deref(), Some("/home/me/.cargo/bin:/usr/bin"));
+    }
+
+    #[test]
+    fn one_or_many_fields_serialize_as_arrays() {
+        let json = |compose: serde_json::Value, app_port: serde_json::Value| {
+            let config: DevcontainerConfig = serde_json::from_value(serde_json::json!({
+                "dockerComposeFile": compose,
+                "service": "app",
+                "appPort": app_port,
+            }))
+            .unwrap();
+            let value = serde_json::to_value(&config).unwrap();
+            let round_trip: DevcontainerConfig = serde_json::from_value(value.clone()).unwrap();
+            assert_eq!(round_trip.docker_compose_file, config.docker_compose_file);
+            assert_eq!(round_trip.app_port, config.app_port);
+            (value["dockerComposeFile"].clone(), value["appPort"].clone())
+        };
+        let arrays = (
+            serde_json::json!(["compose.yml"]),
+            serde_json::json!([3000]),
+        );
+        assert_eq!(
+            json(serde_json::json!("compose.yml"), serde_json::json!(3000)),
+            arrays
+        );
+        assert_eq!(json(arrays.0.clone(), arrays.1.clone()), arrays);
+    }
+
+    #[test]
+    fn workspace_folder_defaults_to_project_folder_name() {
+        let config = load_toml(r#"service = "app""#);
+        assert_eq!(config.workspace_folder, Path::new("/workspaces/repo"));
+        assert!(default_workspace_folder(Path::new("/")).is_err());
+    }
 }
@@ -0,0 +1,129 @@
+use std::fmt;
+use std::str::FromStr;
+
+use schemars::{JsonSchema, Schema, SchemaGenerator, json_schema};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use super::port;
+
+/// A port to publish from the primary container, as in `docker run -p`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct AppPort {
+    pub(crate) host: u16,
+    pub(crate) container: u16,
+}
+
+impl AppPort {
+    /// The compose `ports` entry for this mapping.
+    pub(crate) fn to_compose_port(self) -> String {
+        format!("{}:{}", self.host, self.container)
+    }
+}
+
+impl FromStr for AppPort {
+    type Err = eyre::Report;
+
+    /// Parse `port` or `host:container`.
+    fn from_str(s: &str) -> eyre::Result<Self> {
+        let (host, container) = s.split_once(':').unwrap_or((s, s));
+        Ok(AppPort {
+            host: port::parse(host, s, "host:container")?,
+            container: port::parse(container, s, "host:container")?,
+        })
+    }
+}
+
+impl JsonSchema for AppPort {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "AppPort".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "oneOf": [
+                {
+                    "type": "integer",
+                    "minimum": 0,
+                    "maximum": u16::MAX,
+                    "description": "A port published on the same port on the host.",
+                },
+                {
+                    "type": "string",
+                    "pattern": r"^\d+(:\d+)?$",
+                    "description": "A `host:container` port mapping.",
+                },
+            ]
+        })
+    }
+}
+
+impl fmt::Display for AppPort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.host == self.container {
+            self.host.fmt(f)
+        } else {
+            write!(f, "{}:{}", self.host, self.container)
+        }
+    }
+}
+
+impl Serialize for AppPort {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        if self.host == self.container {
+            serializer.serialize_u16(self.host)
+        } else {
+            serializer.collect_str(&self)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for AppPort {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        port::deserialize(deserializer, |port| AppPort {
+            host: port,
+            container: port,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_number() {
+        let port: AppPort = serde_json::from_str("3000").unwrap();
+        assert_eq!(port.to_compose_port(), "3000:3000");
+    }
+
+    #[test]
+    fn from_string_mapping() {
+        let port: AppPort = serde_json::from_str("\"8000:8010\"").unwrap();
+        assert_eq!(
+            port,
+            AppPort {
+                host: 8000,
+                container: 8010
+            }
+        );
+        assert_eq!(serde_json::to_string(&port).unwrap(), "\"8000:8010\"");
+    }
+
+    #[test]
+    fn from_string_port() {
+        let port: AppPort = serde_json::from_str("\"3000\"").unwrap();
+        assert_eq!(port, "3000:3000".parse().unwrap());
+        assert_eq!(serde_json::to_string(&port).unwrap(), "3000");
+    }
+
+    #[test]
+    fn invalid_string() {
+        assert!(serde_json::from_str::<AppPort>("\"abc\"").is_err());
+        assert!(serde_json::from_str::<AppPort>("\"3000:abc\"").is_err());
+        assert!(serde_json::from_str::<AppPort>("\"1:2:3\"").is_err());
+        assert!("70000".parse::<AppPort>().is_err());
+    }
+}
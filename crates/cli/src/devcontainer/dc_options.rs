@@ -1,4 +1,6 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+use color_eyre::Section;
 
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -12,8 +14,25 @@ use crate::run::cmd::Cmd;
 #[serde(rename_all = "camelCase", default)]
 pub(crate) struct DcOptions {
     pub(crate) default_exec: Option<Cmd>,
+    /// What `dc attach` runs on the host, from the worktree, to attach an editor. It gets
+    /// `DC_CONTAINER_ID`, `DC_WORKSPACE_FOLDER`, and `DC_WORKSPACE` in its environment.
+    pub(crate) attach_command: Option<Cmd>,
+    /// What `dc up` runs on the host once it finishes, whether it succeeded or not, e.g. to send a
+    /// desktop notification. It gets `DC_WORKSPACE` and `DC_STATUS` (`success` or `failure`) in
+    /// its environment.
+    pub(crate) notify_command: Option<Cmd>,
     #[serde(deserialize_with = "deserialize_shell_path_opt")]
     pub(crate) worktree_folder: Option<PathBuf>,
+    /// A directory, relative to the workspace, to run lifecycle commands from: in the container for
+    /// most, and on the host for `initializeCommand`.
+    ///
+    /// Default: the workspace folder in the container, and the worktree on the host
+    lifecycle_workdir: Option<PathBuf>,
+    /// Run string-form lifecycle commands in the container in a login shell (bash, or sh without
+    /// it), so they can use what the profile sets up, like nvm's shell functions.
+    ///
+    /// Default: false
+    pub(crate) login_shell: bool,
     /// Whether to mount the project's git directory into each workspace's devcontainer.
     ///
     /// Git worktrees have a simple `.git` file that points to the actual `.git` directory. If that
@@ -23,14 +42,95 @@ pub(crate) struct DcOptions {
     ///
     /// Defaults to true, but we use Option so it can be overridden.
     mount_git: Option<bool>,
+    /// A file of `KEY=VALUE` lines providing values for `secrets` not set in the host
+    /// environment. Relative paths are resolved from the project root.
+    #[serde(deserialize_with = "deserialize_shell_path_opt")]
+    pub(crate) secrets_file: Option<PathBuf>,
+    /// When `dc up` pulls images: `always`, `missing`, or `never`. Overridden by `dc up --pull`.
+    ///
+    /// Default: "missing"
+    pub(crate) pull: PullPolicy,
+    /// How the container idles once started, with `overrideCommand`: `auto`, `sleepLoop`,
+    /// `sleepInfinity`, or `tailNull`.
+    ///
+    /// Default: "auto"
+    pub(crate) keep_alive: KeepAlive,
     /// Reverse-proxy configuration.
     ///
     /// Leave empty if you don't wish to use it.
     pub(crate) proxy: ProxyOptions,
 }
 
+/// Compose's `--pull` policy for `dc up`.
+#[derive(
+    Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq, JsonSchema, clap::ValueEnum,
+)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum PullPolicy {
+    /// Pull images every time, to pick up new pushes of the same tag.
+    Always,
+    /// Pull only images that aren't present locally.
+    #[default]
+    Missing,
+    /// Never pull; fail if an image is missing.
+    Never,
+}
+
+/// What keeps an `overrideCommand` container running. Each runs in the background under a `wait`,
+/// so the entrypoint can still exit on SIGTERM.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum KeepAlive {
+    /// `sleep infinity`, falling back to `sleepLoop` where `sleep` doesn't support it.
+    #[default]
+    Auto,
+    /// `sleep 1` in a loop: works with any `sleep`, but wakes every second.
+    SleepLoop,
+    /// `sleep infinity`, as GNU coreutils and most busybox builds support.
+    SleepInfinity,
+    /// `tail -f /dev/null`.
+    TailNull,
+}
+
+impl KeepAlive {
+    /// The shell that idles.
+    pub(crate) fn script(self) -> &'static str {
+        match self {
+            // An unsupported `sleep infinity` fails right away.
+            KeepAlive::Auto => "sleep infinity & wait $! || while sleep 1 & wait $!; do :; done",
+            KeepAlive::SleepLoop => "while sleep 1 & wait $!; do :; done",
+            KeepAlive::SleepInfinity => "sleep infinity & wait $!",
+            KeepAlive::TailNull => "tail -f /dev/null & wait $!",
+        }
+    }
+}
+
+impl PullPolicy {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            PullPolicy::Always => "always",
+            PullPolicy::Missing => "missing",
+            PullPolicy::Never => "never",
+        }
+    }
+}
+
 impl DcOptions {
     pub(crate) fn mount_git(&self) -> bool {
         self.mount_git.unwrap_or(true)
     }
+
+    /// Where to run lifecycle commands, given the workspace's root: its folder in the container, or
+    /// its worktree on the host.
+    pub(crate) fn lifecycle_workdir(&self, root: &Path) -> eyre::Result<PathBuf> {
+        match &self.lifecycle_workdir {
+            None => Ok(root.to_path_buf()),
+            Some(dir) if dir.is_relative() => Ok(root.join(dir)),
+            Some(dir) => Err(eyre::eyre!(
+                "lifecycleWorkdir must be relative: {}",
+                dir.display()
+            ))
+            .suggestion("it's resolved from the workspace folder, or the worktree on the host"),
+        }
+    }
 }
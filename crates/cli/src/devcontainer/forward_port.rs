@@ -1,15 +1,32 @@
 use std::fmt;
+use std::str::FromStr;
 
 use schemars::{JsonSchema, Schema, SchemaGenerator, json_schema};
-use serde::de::{self, Unexpected};
 use serde::{Deserialize, Deserializer, Serialize};
 
+use super::port;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) struct ForwardPort {
     pub(crate) service: Option<String>,
     pub(crate) port: u16,
 }
 
+impl FromStr for ForwardPort {
+    type Err = eyre::Report;
+
+    /// Parse `port` or `service:port`.
+    fn from_str(s: &str) -> eyre::Result<Self> {
+        let (service, port) = match s.split_once(':') {
+            Some((service, port)) if !service.is_empty() => (Some(service.to_owned()), port),
+            Some(_) => (None, ""),
+            None => (None, s),
+        };
+        let port = port::parse(port, s, "service:port")?;
+        Ok(ForwardPort { service, port })
+    }
+}
+
 impl JsonSchema for ForwardPort {
     fn schema_name() -> std::borrow::Cow<'static, str> {
         "ForwardPort".into()
@@ -26,7 +43,7 @@ impl JsonSchema for ForwardPort {
                 },
                 {
                     "type": "string",
-                    "pattern": r"^[^:]+:\d+$",
+                    "pattern": r"^([^:]+:)?\d+$",
                     "description": "A `service:port` mapping selecting a compose service.",
                 },
             ]
@@ -61,35 +78,10 @@ impl Serialize for ForwardPort {
 
 impl<'de> Deserialize<'de> for ForwardPort {
     fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
-        #[derive(Deserialize)]
-        #[serde(untagged)]
-        enum Raw {
-            Number(u16),
-            String(String),
-        }
-
-        match Raw::deserialize(deserializer)? {
-            Raw::Number(port) => Ok(ForwardPort {
-                service: None,
-                port,
-            }),
-            Raw::String(s) => {
-                if let Some((service, port)) = s.split_once(':') {
-                    let port = port.parse::<u16>().map_err(|_| {
-                        de::Error::invalid_value(Unexpected::Str(&s), &"a valid port mapping")
-                    })?;
-                    Ok(ForwardPort {
-                        service: Some(service.to_string()),
-                        port,
-                    })
-                } else {
-                    Err(de::Error::invalid_value(
-                        Unexpected::Str(&s),
-                        &"a port number or \"service:port\" mapping",
-                    ))
-                }
-            }
-        }
+        port::deserialize(deserializer, |port| ForwardPort {
+            service: None,
+            port,
+        })
     }
 }
 
@@ -139,9 +131,22 @@ mod tests {
         assert_eq!(serde_json::to_string(&pm).unwrap(), "3001");
     }
 
+    #[test]
+    fn from_string_port() {
+        let pm: ForwardPort = serde_json::from_str("\"3000\"").unwrap();
+        assert_eq!(
+            pm,
+            ForwardPort {
+                service: None,
+                port: 3000
+            }
+        );
+    }
+
     #[test]
     fn invalid_string() {
         assert!(serde_json::from_str::<ForwardPort>("\"abc\"").is_err());
+        assert!(serde_json::from_str::<ForwardPort>("\":3000\"").is_err());
     }
 
     #[test]
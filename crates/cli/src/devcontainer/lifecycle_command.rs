@@ -6,7 +6,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::run::Runner;
 use crate::run::cmd::{Cmd, NamedCmd};
-use crate::run::docker_exec::DockerExec;
+use crate::run::docker_exec::{DockerExec, ExecContext};
 
 #[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
 #[serde(untagged)]
@@ -37,31 +37,18 @@ impl LifecycleCommand {
     pub(crate) async fn run_in_container(
         &self,
         name: &str,
-        container: &str,
-        user: Option<&str>,
-        workdir: Option<&Path>,
-        env: &IndexMap<String, Option<String>>,
+        context: ExecContext<'_>,
     ) -> eyre::Result<()> {
         match self {
             LifecycleCommand::Single(cmd) => {
-                let exec = DockerExec {
-                    name,
-                    container,
-                    cmd,
-                    user,
-                    workdir,
-                    env,
-                };
+                let exec = DockerExec { name, cmd, context };
                 Runner::run(exec).await
             }
             LifecycleCommand::Parallel(map) => {
                 let execs = map.iter().map(|(cmd_name, cmd)| DockerExec {
                     name: cmd_name,
-                    container,
                     cmd,
-                    user,
-                    workdir,
-                    env,
+                    context,
                 });
 
                 Runner::run_parallel(name, execs).await
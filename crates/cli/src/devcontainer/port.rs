@@ -0,0 +1,36 @@
+//! Parsing shared by [`AppPort`](super::app_port::AppPort) and
+//! [`ForwardPort`](super::forward_port::ForwardPort). Each is written as a bare
+//! port number, or as a string that its `FromStr` parses.
+
+use std::str::FromStr;
+
+use eyre::eyre;
+use serde::{Deserialize, Deserializer, de};
+
+/// Deserialize a port number with `from_number`, or a string with `T::from_str`.
+pub(crate) fn deserialize<'de, D, T>(
+    deserializer: D,
+    from_number: impl FnOnce(u16) -> T,
+) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr<Err = eyre::Report>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Raw {
+        Number(u16),
+        String(String),
+    }
+
+    match Raw::deserialize(deserializer)? {
+        Raw::Number(port) => Ok(from_number(port)),
+        Raw::String(s) => s.parse().map_err(de::Error::custom),
+    }
+}
+
+/// Parse `port`, a part of `spec`, which should be a port or a `mapping`.
+pub(crate) fn parse(port: &str, spec: &str, mapping: &str) -> eyre::Result<u16> {
+    port.parse()
+        .map_err(|_| eyre!("invalid port {spec:?}; expected a port or {mapping:?}"))
+}
@@ -0,0 +1,112 @@
+//! Translate `runArgs` into the compose override.
+//!
+//! We only run compose configs, so there is no `docker run` to hand these to. Instead, the
+//! common flags are mapped onto the primary service; anything else is reported back so the
+//! caller can warn about it.
+
+use serde_json::{Value, json};
+
+/// How a `docker run` flag lands in a compose service.
+enum Target {
+    /// Appended to a list key.
+    List(&'static str),
+    /// Sets a scalar key.
+    Value(&'static str),
+    /// A boolean switch that takes no value.
+    Switch(&'static str),
+}
+
+fn target(flag: &str) -> Option<Target> {
+    Some(match flag {
+        "--cap-add" => Target::List("cap_add"),
+        "--cap-drop" => Target::List("cap_drop"),
+        "--device" => Target::List("devices"),
+        "--security-opt" => Target::List("security_opt"),
+        "--add-host" => Target::List("extra_hosts"),
+        "--dns" => Target::List("dns"),
+        "--network" | "--net" => Target::Value("network_mode"),
+        "--shm-size" => Target::Value("shm_size"),
+        "-h" | "--hostname" => Target::Value("hostname"),
+        "--privileged" => Target::Switch("privileged"),
+        "--init" => Target::Switch("init"),
+        _ => return None,
+    })
+}
+
+/// Apply `args` to `service`, returning the arguments that could not be represented.
+pub(crate) fn apply(args: &[String], service: &mut Value) -> Vec<String> {
+    let mut unsupported = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with('-') => (flag, Some(value.to_owned())),
+            _ => (arg.as_str(), None),
+        };
+        let Some(target) = target(flag) else {
+            unsupported.push(arg.clone());
+            continue;
+        };
+        match target {
+            Target::Switch(key) => {
+                service[key] = json!(inline.as_deref() != Some("false"));
+            }
+            Target::List(key) | Target::Value(key) => {
+                let Some(value) = inline.or_else(|| iter.next().cloned()) else {
+                    unsupported.push(arg.clone());
+                    continue;
+                };
+                if let Target::List(_) = target {
+                    match service[key].as_array_mut() {
+                        Some(list) => list.push(json!(value)),
+                        None => service[key] = json!([value]),
+                    }
+                } else {
+                    service[key] = json!(value);
+                }
+            }
+        }
+    }
+    unsupported
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn maps_common_flags() {
+        let mut service = json!({ "cap_add": ["SYS_ADMIN"] });
+        let unsupported = apply(
+            &args(&[
+                "--cap-add=SYS_PTRACE",
+                "--device",
+                "/dev/fuse",
+                "--network=host",
+                "--init",
+            ]),
+            &mut service,
+        );
+        assert!(unsupported.is_empty());
+        assert_eq!(
+            service,
+            json!({
+                "cap_add": ["SYS_ADMIN", "SYS_PTRACE"],
+                "devices": ["/dev/fuse"],
+                "network_mode": "host",
+                "init": true,
+            })
+        );
+    }
+
+    #[test]
+    fn reports_unknown_flags() {
+        let mut service = json!({});
+        let unsupported = apply(&args(&["--gpus=all", "--dns"]), &mut service);
+        assert_eq!(unsupported, ["--gpus=all", "--dns"]);
+        assert_eq!(service, json!({}));
+    }
+}
@@ -74,6 +74,11 @@ impl<'a> Context<'a> {
         self
     }
 
+    /// The container's environment, including `containerEnv`, if it's known.
+    pub(crate) fn container_env(&self) -> Option<&IndexMap<String, String>> {
+        self.container.as_ref().map(|c| &c.env)
+    }
+
     #[cfg(test)]
     fn with_local_env(mut self, local_env: IndexMap<String, String>) -> Self {
         self.local_env = local_env;
@@ -1,12 +1,14 @@
 use std::collections::HashMap;
 use std::path::Path;
+use std::time::Instant;
 
 use docker::{
     COMPOSE_PROJECT_LABEL, COMPOSE_SERVICE_LABEL, FORWARD_LABEL, FORWARD_TARGET_LABEL,
-    LOCAL_FOLDER_LABEL, PROJECT_LABEL, WORKSPACE_LABEL,
+    LOCAL_FOLDER_LABEL, MANAGED_LABEL, PROJECT_LABEL, WORKSPACE_LABEL,
 };
 use eyre::WrapErr;
 use futures::future::try_join_all;
+use tokio::sync::OnceCell;
 
 use crate::workspace::Workspace;
 
@@ -25,15 +27,34 @@ pub(crate) struct ContainerInfo {
 
 /// Raw single-container sample with the CPU counters needed to diff a
 /// percentage. Not summable: `system_cpu` is host-wide, identical per container.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone)]
 pub(crate) struct StatsSample {
     pub(crate) ram: u64,
     /// Cumulative container CPU time (ns).
     pub(crate) cpu_total: u64,
     /// Host-wide cumulative CPU time (ns), when reported.
     pub(crate) system_cpu: Option<u64>,
-    /// Online CPU count, when reported.
-    pub(crate) online_cpus: Option<u32>,
+    /// CPU count: `online_cpus`, else the `percpu_usage` length (cgroup v1), else the daemon's.
+    pub(crate) cpus: Option<u32>,
+    /// When the sample was taken, for diffing without `system_cpu`.
+    pub(crate) at: Instant,
+}
+
+impl StatsSample {
+    pub(crate) fn new(stats: docker::ContainerStats) -> Self {
+        let cpu = stats.cpu_stats;
+        let percpu = cpu
+            .cpu_usage
+            .percpu_usage
+            .and_then(|usage| u32::try_from(usage.len()).ok());
+        Self {
+            ram: stats.memory_stats.usage.unwrap_or_default(),
+            cpu_total: cpu.cpu_usage.total_usage,
+            system_cpu: cpu.system_cpu_usage,
+            cpus: cpu.online_cpus.or(percpu).filter(|&n| n > 0),
+            at: Instant::now(),
+        }
+    }
 }
 
 fn container_info_from(c: docker::ContainerSummary) -> ContainerInfo {
@@ -49,16 +70,54 @@ fn container_info_from(c: docker::ContainerSummary) -> ContainerInfo {
     }
 }
 
+/// A `dc fwd` forward: `host` on the host reaches `container` in the target container. They
+/// differ when `--auto-port` moved a busy port.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) struct Forward {
+    pub(crate) host: u16,
+    pub(crate) container: u16,
+}
+
+/// A running `docker exec` process.
+#[derive(Debug, Clone)]
+pub(crate) struct ExecSession {
+    /// The exec's id, for inspecting it again.
+    pub(crate) id: String,
+    /// PID in the daemon's PID namespace, as Docker reports it.
+    pub(crate) pid: u32,
+    pub(crate) command: String,
+}
+
+/// Our handle on the Docker daemon. Connects on first use, so commands that never touch Docker
+/// work without it.
 pub(crate) struct DockerClient {
-    pub(crate) client: docker::Docker,
+    client: OnceCell<docker::Docker>,
+    /// The daemon's CPU count, for stats that don't report one.
+    host_cpus: OnceCell<u32>,
+    /// Our [`MANAGED_LABEL`] value; see [`Config::managed_label_value`].
+    ///
+    /// [`Config::managed_label_value`]: crate::config::Config::managed_label_value
+    pub(crate) namespace: String,
 }
 
 impl DockerClient {
-    pub(crate) async fn new() -> eyre::Result<Self> {
-        let client = docker::Docker::connect()
+    pub(crate) fn new(namespace: &str) -> Self {
+        Self {
+            client: OnceCell::new(),
+            host_cpus: OnceCell::new(),
+            namespace: namespace.to_owned(),
+        }
+    }
+
+    /// The connected client, connecting if this is the first use.
+    pub(crate) async fn client(&self) -> eyre::Result<&docker::Docker> {
+        self.client
+            .get_or_try_init(|| async {
+                docker::Docker::connect()
+                    .await
+                    .wrap_err("failed to connect to Docker")
+            })
             .await
-            .wrap_err("failed to connect to Docker")?;
-        Ok(Self { client })
     }
 
     /// Return containers for a specific workspace, filtered at the Docker API level.
@@ -76,9 +135,12 @@ impl DockerClient {
         path: &Path,
     ) -> eyre::Result<Vec<ContainerInfo>> {
         let summaries = self
-            .client
+            .client()
+            .await?
             .list_containers()
             .all(true)
+            // Editors set `local_folder` too; only our own containers count.
+            .with_label(MANAGED_LABEL, &self.namespace)
             .with_label(LOCAL_FOLDER_LABEL, path.display().to_string())
             .call()
             .await?;
@@ -92,7 +154,8 @@ impl DockerClient {
         compose_project: &str,
     ) -> eyre::Result<Vec<ContainerInfo>> {
         let summaries = self
-            .client
+            .client()
+            .await?
             .list_containers()
             .all(true)
             .with_label(COMPOSE_PROJECT_LABEL, compose_project)
@@ -103,23 +166,32 @@ impl DockerClient {
 
     /// A one-shot stats sample for a container, with the CPU counters.
     pub(crate) async fn stats_sample(&self, container_id: &str) -> eyre::Result<StatsSample> {
-        let stats = self.client.stats(container_id).await?;
-        Ok(StatsSample {
-            ram: stats.memory_stats.usage.unwrap_or_default(),
-            cpu_total: stats.cpu_stats.cpu_usage.total_usage,
-            system_cpu: stats.cpu_stats.system_cpu_usage,
-            online_cpus: stats.cpu_stats.online_cpus,
-        })
+        let stats = self.client().await?.stats(container_id).await?;
+        let mut sample = StatsSample::new(stats);
+        if sample.cpus.is_none() {
+            sample.cpus = self.host_cpus().await.ok().filter(|&n| n > 0);
+        }
+        Ok(sample)
     }
 
-    /// Ports forwarded by `dc fwd`.
+    /// The daemon's CPU count, from `/info`; asked once.
+    async fn host_cpus(&self) -> eyre::Result<u32> {
+        self.host_cpus
+            .get_or_try_init(|| async { Ok(self.client().await?.info().await?.ncpu) })
+            .await
+            .copied()
+    }
+
+    /// Ports forwarded by `dc fwd`, per workspace.
     pub(crate) async fn forwarded_ports(
         &self,
         project: &str,
-    ) -> eyre::Result<HashMap<String, Vec<u16>>> {
+    ) -> eyre::Result<HashMap<String, Vec<Forward>>> {
         let summaries = self
-            .client
+            .client()
+            .await?
             .list_containers()
+            .with_label(MANAGED_LABEL, &self.namespace)
             .with_label(FORWARD_LABEL, "true")
             .with_label(PROJECT_LABEL, project)
             .call()
@@ -128,8 +200,20 @@ impl DockerClient {
         let result = summaries
             .into_iter()
             .filter_map(|c| {
-                let ws = c.labels.get(WORKSPACE_LABEL)?.clone();
-                let ports: Vec<u16> = c.ports.into_iter().filter_map(|p| p.public_port).collect();
+                let Some(ws) = c.labels.get(WORKSPACE_LABEL).cloned() else {
+                    tracing::debug!(container = %c.id, "forward sidecar has no workspace label");
+                    return None;
+                };
+                let ports: Vec<Forward> = c
+                    .ports
+                    .into_iter()
+                    .filter_map(|p| {
+                        Some(Forward {
+                            host: p.public_port?,
+                            container: p.private_port,
+                        })
+                    })
+                    .collect();
                 if ports.is_empty() {
                     None
                 } else {
@@ -143,16 +227,105 @@ impl DockerClient {
         Ok(result)
     }
 
+    /// Host ports held by `dc fwd` sidecars of any project, keyed to the
+    /// `project/workspace` that owns them.
+    pub(crate) async fn forward_owners(&self) -> eyre::Result<HashMap<u16, String>> {
+        let summaries = self
+            .client()
+            .await?
+            .list_containers()
+            .with_label(FORWARD_LABEL, "true")
+            .call()
+            .await?;
+
+        let mut owners = HashMap::new();
+        for c in summaries {
+            let (Some(project), Some(ws)) =
+                (c.labels.get(PROJECT_LABEL), c.labels.get(WORKSPACE_LABEL))
+            else {
+                tracing::debug!(container = %c.id, "forward sidecar is missing our labels");
+                continue;
+            };
+            for port in c.ports.iter().filter_map(|p| p.public_port) {
+                owners.insert(port, format!("{project}/{ws}"));
+            }
+        }
+        Ok(owners)
+    }
+
+    /// Whether `dc fwd` sidecars exist for this workspace, running or not.
+    pub(crate) async fn has_forward_sidecars(
+        &self,
+        workspace: &Workspace<'_>,
+    ) -> eyre::Result<bool> {
+        let sidecars = self
+            .client()
+            .await?
+            .list_containers()
+            .all(true)
+            .with_label(PROJECT_LABEL, workspace.state.project_name.as_str())
+            .with_label(WORKSPACE_LABEL, workspace.name.as_str())
+            .with_label(MANAGED_LABEL, &self.namespace)
+            .with_label(FORWARD_LABEL, "true")
+            .call()
+            .await?;
+        Ok(!sidecars.is_empty())
+    }
+
+    /// Workspaces of `project` whose `dc fwd` sidecars target a container that is gone or no
+    /// longer running, e.g. after a crash or a recreate.
+    pub(crate) async fn stale_forwards(&self, project: &str) -> eyre::Result<Vec<String>> {
+        let client = self.client().await?;
+        let sidecars = client
+            .list_containers()
+            .all(true)
+            .with_label(PROJECT_LABEL, project)
+            .with_label(MANAGED_LABEL, &self.namespace)
+            .with_label(FORWARD_LABEL, "true")
+            .call()
+            .await?;
+
+        let mut targets: HashMap<String, Vec<String>> = HashMap::new();
+        for c in sidecars {
+            let (Some(target), Some(ws)) = (
+                c.labels.get(FORWARD_TARGET_LABEL),
+                c.labels.get(WORKSPACE_LABEL),
+            ) else {
+                tracing::debug!(container = %c.id, "forward sidecar is missing labels");
+                continue;
+            };
+            targets.entry(target.clone()).or_default().push(ws.clone());
+        }
+
+        let mut stale = Vec::new();
+        for (target, workspaces) in targets {
+            let running = client
+                .list_containers()
+                .with_id(target)
+                .with_status(docker::ContainerStatus::Running)
+                .call()
+                .await?;
+            if running.is_empty() {
+                stale.extend(workspaces);
+            }
+        }
+        stale.sort();
+        stale.dedup();
+        Ok(stale)
+    }
+
     pub(crate) async fn is_forwarding_healthy(
         &self,
         workspace: &Workspace<'_>,
     ) -> eyre::Result<bool> {
         let sidecars = self
-            .client
+            .client()
+            .await?
             .list_containers()
             .all(true)
             .with_label(PROJECT_LABEL, workspace.state.project_name.as_str())
             .with_label(WORKSPACE_LABEL, workspace.name.as_str())
+            .with_label(MANAGED_LABEL, &self.namespace)
             .with_label(FORWARD_LABEL, "true")
             .call()
             .await?;
@@ -166,7 +339,8 @@ impl DockerClient {
         };
 
         let targets = self
-            .client
+            .client()
+            .await?
             .list_containers()
             .with_id(target_id)
             .with_status(docker::ContainerStatus::Running)
@@ -180,10 +354,12 @@ impl DockerClient {
         workspace: &Workspace<'_>,
     ) -> eyre::Result<Vec<u16>> {
         let summaries = self
-            .client
+            .client()
+            .await?
             .list_containers()
             .with_label(PROJECT_LABEL, workspace.state.project_name.as_str())
             .with_label(WORKSPACE_LABEL, workspace.name.as_str())
+            .with_label(MANAGED_LABEL, &self.namespace)
             .with_label(FORWARD_LABEL, "true")
             .call()
             .await?;
@@ -204,7 +380,8 @@ impl DockerClient {
         workspace: &Workspace<'_>,
     ) -> eyre::Result<Vec<(String, String)>> {
         let summaries = self
-            .client
+            .client()
+            .await?
             .list_containers()
             .all(true)
             .with_label(COMPOSE_PROJECT_LABEL, workspace.compose_project_name())
@@ -230,24 +407,39 @@ impl DockerClient {
     }
 
     pub(crate) async fn execs(&self, container_id: &str) -> eyre::Result<usize> {
-        let info = self
-            .client
+        Ok(self.exec_sessions(container_id).await?.len())
+    }
+
+    /// The execs still running in a container.
+    pub(crate) async fn exec_sessions(&self, container_id: &str) -> eyre::Result<Vec<ExecSession>> {
+        let client = self.client().await?;
+        let info = client
             .inspect_container(container_id)
             .await
             .wrap_err_with(|| format!("failed to inspect container {container_id}"))?;
 
-        let futures = info
-            .exec_ids
-            .into_iter()
-            .map(async |eid| -> eyre::Result<bool> {
-                Ok(self.client.inspect_exec(&eid).await?.running)
-            });
+        // All at once; editors can leave dozens of finished execs behind.
+        let futures = info.exec_ids.iter().map(|eid| client.inspect_exec(eid));
 
-        let execs = try_join_all(futures)
+        let sessions = try_join_all(futures)
             .await?
             .into_iter()
-            .filter(|r| *r)
-            .count();
-        Ok(execs)
+            .filter(|e| e.running)
+            .map(|e| ExecSession {
+                id: e.id,
+                pid: e.pid,
+                command: std::iter::once(e.process_config.entrypoint)
+                    .chain(e.process_config.arguments)
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            })
+            .collect();
+        Ok(sessions)
+    }
+
+    /// Whether the exec `exec_id` is still running as `pid`.
+    pub(crate) async fn exec_running_as(&self, exec_id: &str, pid: u32) -> eyre::Result<bool> {
+        let exec = self.client().await?.inspect_exec(exec_id).await?;
+        Ok(exec.running && exec.pid == pid)
     }
 }
@@ -1,26 +1,119 @@
-use std::path::PathBuf;
+use std::ops::{Deref, DerefMut};
+use std::path::{Component, Path, PathBuf};
+use std::process::Stdio;
 
+use color_eyre::Section;
 use docker::{LOCAL_FOLDER_LABEL, MANAGED_LABEL, PROJECT_LABEL, WORKSPACE_LABEL};
 use eyre::{Context, eyre};
+use indexmap::IndexMap;
+use itertools::Itertools;
 use serde_json::json;
+use tokio::io::AsyncWriteExt;
 
-use crate::devcontainer::substitution;
+use crate::devcontainer::forward_port::ForwardPort;
+use crate::devcontainer::{DevcontainerConfig, MountEntry, run_args, substitution};
+use crate::paths;
 use crate::{state::DevcontainerState, workspace::Workspace};
 
-fn override_path(workspace: &Workspace) -> PathBuf {
-    workspace
+/// Compose settings from the environment that would fight with the project and files we pass.
+pub(crate) const IGNORED_ENV: [&str; 3] =
+    ["COMPOSE_FILE", "COMPOSE_PROJECT_NAME", "COMPOSE_PROFILES"];
+
+fn override_file_name(workspace: &Workspace) -> String {
+    format!("{}-override.yml", workspace.name)
+}
+
+/// The generated compose override for `workspace`, in the [state dir](paths::state_dir).
+pub(crate) fn override_path(workspace: &Workspace) -> eyre::Result<PathBuf> {
+    Ok(paths::state_dir()?
+        .join(workspace.state.project_name.as_str())
+        .join(override_file_name(workspace)))
+}
+
+/// The compose file generated for an image or Dockerfile devcontainer, next to the override.
+fn single_container_path(override_path: &Path) -> PathBuf {
+    let name = override_path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy();
+    let name = name.strip_suffix("-override.yml").unwrap_or(&name);
+    override_path.with_file_name(format!("{name}-compose.yml"))
+}
+
+pub(crate) fn remove_override_file(workspace: &Workspace) {
+    // Overrides used to be written next to the worktrees.
+    let legacy = workspace
         .state
         .project_working_dir()
-        .join(format!("{}-override.yml", workspace.name))
+        .join(override_file_name(workspace));
+    let paths = override_path(workspace)
+        .inspect_err(|e| eprintln!("warning: {e}"))
+        .into_iter()
+        .flat_map(|path| [single_container_path(&path), path])
+        .chain([legacy]);
+
+    for path in paths {
+        if path.exists()
+            && let Err(e) = std::fs::remove_file(&path)
+        {
+            eprintln!("warning: failed to remove {}: {e}", path.display());
+        }
+    }
 }
 
-pub(crate) fn remove_override_file(workspace: &Workspace) {
-    let path = override_path(workspace);
+/// A `docker compose` command for a workspace, along with the override file it reads.
+///
+/// An override written fresh for this command is removed when it's dropped, along with any
+/// generated compose file, unless kept with [`ComposeCmd::keep`]: a failed `up` of a new workspace
+/// shouldn't leave one behind. Overrides of workspaces that already had one are left alone.
+pub(crate) struct ComposeCmd {
+    cmd: tokio::process::Command,
+    override_path: PathBuf,
+    remove: bool,
+}
 
-    if path.exists()
-        && let Err(e) = std::fs::remove_file(&path)
-    {
-        eprintln!("warning: failed to remove {}: {e}", path.display());
+impl ComposeCmd {
+    /// Keep the override file; the workspace now exists and will use it.
+    pub(crate) fn keep(&mut self) {
+        self.remove = false;
+    }
+
+    /// The command, keeping the override file.
+    pub(crate) fn into_inner(mut self) -> tokio::process::Command {
+        self.keep();
+        std::mem::replace(&mut self.cmd, tokio::process::Command::new("docker"))
+    }
+}
+
+impl Deref for ComposeCmd {
+    type Target = tokio::process::Command;
+
+    fn deref(&self) -> &Self::Target {
+        &self.cmd
+    }
+}
+
+impl DerefMut for ComposeCmd {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.cmd
+    }
+}
+
+impl Drop for ComposeCmd {
+    fn drop(&mut self) {
+        if !self.remove {
+            return;
+        }
+        for path in [
+            single_container_path(&self.override_path),
+            self.override_path.clone(),
+        ] {
+            if let Err(e) = std::fs::remove_file(&path)
+                && e.kind() != std::io::ErrorKind::NotFound
+            {
+                tracing::warn!("failed to remove {}: {e}", path.display());
+            }
+        }
     }
 }
 
@@ -28,21 +121,358 @@ pub(crate) fn remove_override_file(workspace: &Workspace) {
 pub(crate) fn compose_cmd(
     devcontainer: &DevcontainerState,
     workspace: &Workspace,
-) -> eyre::Result<tokio::process::Command> {
-    let override_file_path = write_compose_override(devcontainer, workspace)?;
+) -> eyre::Result<ComposeCmd> {
+    compose_cmd_publishing(devcontainer, workspace, false)
+}
+
+/// [`compose_cmd`], with `forwardPorts` published through the override if `publish` is set, as
+/// for `dc up --publish`.
+pub(crate) fn compose_cmd_publishing(
+    devcontainer: &DevcontainerState,
+    workspace: &Workspace,
+    publish: bool,
+) -> eyre::Result<ComposeCmd> {
+    compose_cmd_with(
+        devcontainer,
+        workspace,
+        &workspace.compose_project_name(),
+        publish,
+    )
+}
+
+/// [`compose_cmd`], for the compose project `project` rather than the one the workspace's name
+/// gives.
+pub(crate) fn compose_cmd_for(
+    devcontainer: &DevcontainerState,
+    workspace: &Workspace,
+    project: &str,
+) -> eyre::Result<ComposeCmd> {
+    compose_cmd_with(devcontainer, workspace, project, false)
+}
 
+fn compose_cmd_with(
+    devcontainer: &DevcontainerState,
+    workspace: &Workspace,
+    project: &str,
+    publish: bool,
+) -> eyre::Result<ComposeCmd> {
     let mut cmd = tokio::process::Command::new("docker");
+    for var in IGNORED_ENV {
+        cmd.env_remove(var);
+    }
 
-    cmd.args(["compose", "-p"])
-        .arg(workspace.compose_project_name());
+    cmd.args(["compose", "-p"]).arg(project);
+    add_compose_files(&mut cmd, devcontainer, workspace)?;
 
-    for f in &devcontainer.config.docker_compose_file {
-        cmd.arg("-f")
-            .arg(workspace.path.join(".devcontainer").join(f));
+    let existed = override_path(workspace)?.exists();
+    let override_path = write_compose_override(devcontainer, workspace, publish)?;
+    // Last, so it wins.
+    cmd.arg("-f").arg(&override_path);
+    Ok(ComposeCmd {
+        cmd,
+        override_path,
+        remove: !existed,
+    })
+}
+
+/// Add `-f` for each of the configured compose files, in order, erroring if any are missing.
+///
+/// For an image or Dockerfile devcontainer, write and add the [`single_container_compose`] file
+/// instead.
+fn add_compose_files(
+    cmd: &mut tokio::process::Command,
+    devcontainer: &DevcontainerState,
+    workspace: &Workspace,
+) -> eyre::Result<()> {
+    if devcontainer.config.is_single_container() {
+        let path = single_container_path(&override_path(workspace)?);
+        let compose = single_container_compose(
+            &devcontainer.config,
+            &config_dir(devcontainer, workspace),
+            &workspace.path,
+        )?;
+        write_json(&path, &compose)?;
+        cmd.arg("-f").arg(path);
+        return Ok(());
+    }
+
+    let (config_dir, files) = compose_files(devcontainer, workspace);
+    let missing: Vec<_> = files.iter().filter(|f| !f.is_file()).collect();
+    if !missing.is_empty() {
+        return Err(eyre!(
+            "compose file(s) not found: {}",
+            missing.iter().map(|f| f.display()).join(", ")
+        ))
+        .suggestion(format!(
+            "`dockerComposeFile` paths are relative to {}",
+            config_dir.display()
+        ));
     }
 
-    cmd.arg("-f").arg(override_file_path);
-    Ok(cmd)
+    for f in files {
+        cmd.arg("-f").arg(f);
+    }
+    Ok(())
+}
+
+/// The directory of the devcontainer.json, which the paths in it are relative to.
+fn config_dir(devcontainer: &DevcontainerState, workspace: &Workspace) -> PathBuf {
+    // Without a devcontainer.json, the config comes from the project's config; take it to be
+    // where one would usually be.
+    match devcontainer.path.as_deref().and_then(Path::parent) {
+        Some(dir) => dir.to_owned(),
+        None => workspace.path.join(".devcontainer"),
+    }
+}
+
+/// The directory `dockerComposeFile` entries are relative to, and the files they name.
+fn compose_files(
+    devcontainer: &DevcontainerState,
+    workspace: &Workspace,
+) -> (PathBuf, Vec<PathBuf>) {
+    let config_dir = config_dir(devcontainer, workspace);
+    let files = devcontainer
+        .config
+        .docker_compose_file
+        .iter()
+        .map(|f| resolve_compose_file(&config_dir, f))
+        .collect();
+    (config_dir, files)
+}
+
+/// The base `docker compose` command line [`compose_cmd`] would run, for display. Nothing is
+/// written or checked.
+pub(crate) fn describe_compose_cmd(
+    devcontainer: &DevcontainerState,
+    workspace: &Workspace,
+) -> eyre::Result<String> {
+    let mut line = format!("docker compose -p {}", workspace.compose_project_name());
+    let override_path = override_path(workspace)?;
+    let files = if devcontainer.config.is_single_container() {
+        vec![single_container_path(&override_path)]
+    } else {
+        compose_files(devcontainer, workspace).1
+    };
+    for file in files {
+        line.push_str(&format!(" -f {}", file.display()));
+    }
+    line.push_str(&format!(" -f {}", override_path.display()));
+    Ok(line)
+}
+
+/// A compose file with a single service for an image or Dockerfile devcontainer, so the rest of
+/// it can go through compose like any other: built from `build` (or `dockerFile`), or run from
+/// `image`, with the workspace mounted per `workspaceMount`.
+fn single_container_compose(
+    config: &DevcontainerConfig,
+    config_dir: &Path,
+    workspace_path: &Path,
+) -> eyre::Result<serde_json::Value> {
+    let mut service = json!({});
+    if let Some((dockerfile, context)) = config.dockerfile() {
+        let mut build = json!({
+            "context": resolve_compose_file(config_dir, context),
+            "dockerfile": resolve_compose_file(config_dir, dockerfile),
+        });
+        if let Some(build_options) = &config.build {
+            if let Some(target) = &build_options.target {
+                build["target"] = json!(target);
+            }
+            if !build_options.args.is_empty() {
+                build["args"] = json!(build_options.args);
+            }
+        }
+        service["build"] = build;
+    }
+    if let Some(image) = &config.image {
+        // With `build` too, compose tags the built image with this.
+        service["image"] = json!(image);
+    }
+
+    let mount = match &config.workspace_mount {
+        Some(mount) => {
+            let context = substitution::Context::new(workspace_path, &config.workspace_folder);
+            MountEntry::String(mount.clone())
+                .to_compose_volume(&context)
+                .wrap_err("invalid `workspaceMount`")?
+        }
+        None => format!(
+            "{}:{}",
+            workspace_path.display(),
+            config.workspace_folder.display()
+        ),
+    };
+    service["volumes"] = json!([mount]);
+    service["working_dir"] = json!(config.workspace_folder);
+
+    Ok(json!({
+        "services": { &config.service: service }
+    }))
+}
+
+/// `dockerComposeFile` entries are relative to the devcontainer.json, and often reach outside
+/// its directory (`../docker-compose.yml`). Resolve `..` here, so compose sees a plain path.
+fn resolve_compose_file(config_dir: &Path, file: &str) -> PathBuf {
+    let mut path = PathBuf::new();
+    for component in config_dir.join(file).components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                path.pop();
+            }
+            c => path.push(c),
+        }
+    }
+    path
+}
+
+/// Check that `service` and `runServices` are defined in the compose files, so a typo fails
+/// before anything is built, and return the services for `docker compose up` to name.
+///
+/// With `runServices`, those are it and `service`, plus everything they transitively
+/// `depends_on`: naming a dependency starts it even when it's behind a profile, which compose
+/// would otherwise refuse. Without `runServices`, it's `None`, for compose to start them all.
+///
+/// If compose can't list the services, we leave it to `docker compose up` to complain.
+pub(crate) async fn check_services(
+    devcontainer: &DevcontainerState,
+    workspace: &Workspace<'_>,
+) -> eyre::Result<Option<Vec<String>>> {
+    let project = compose_config(devcontainer, workspace).await?;
+    services_to_start(&devcontainer.config, project.as_ref())
+}
+
+/// The configuration `docker compose config` resolves from the compose files, without the
+/// override, which defines `service` whether or not the compose files do. `None` if compose
+/// can't read them; `up` will say why.
+///
+/// Nothing is written: an image or Dockerfile devcontainer's [`single_container_compose`] goes
+/// through stdin.
+pub(crate) async fn compose_config(
+    devcontainer: &DevcontainerState,
+    workspace: &Workspace<'_>,
+) -> eyre::Result<Option<serde_json::Value>> {
+    let mut cmd = tokio::process::Command::new("docker");
+    for var in IGNORED_ENV {
+        cmd.env_remove(var);
+    }
+    cmd.arg("compose");
+    let stdin = if devcontainer.config.is_single_container() {
+        cmd.args(["-f", "-"]).stdin(Stdio::piped());
+        let compose = single_container_compose(
+            &devcontainer.config,
+            &config_dir(devcontainer, workspace),
+            &workspace.path,
+        )?;
+        Some(serde_json::to_vec(&compose)?)
+    } else {
+        add_compose_files(&mut cmd, devcontainer, workspace)?;
+        None
+    };
+    cmd.args(["--profile", "*", "config", "--format", "json"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    let mut child = cmd.spawn().wrap_err("failed to run docker compose")?;
+    if let (Some(content), Some(mut pipe)) = (stdin, child.stdin.take()) {
+        pipe.write_all(&content).await?;
+    }
+    let out = child.wait_with_output().await?;
+    if !out.status.success() {
+        tracing::debug!(
+            "not checking services; docker compose config failed: {}",
+            String::from_utf8_lossy(&out.stderr).trim()
+        );
+        return Ok(None);
+    }
+    let project =
+        serde_json::from_slice(&out.stdout).wrap_err("failed to parse docker compose config")?;
+    Ok(Some(project))
+}
+
+/// The services `up` should name: `service` and `runServices`, followed by what they depend on
+/// in `project`, from [`compose_config`]. `None` to start them all, when there's no
+/// `runServices`. Errors if `project` is missing any of them.
+pub(crate) fn services_to_start(
+    config: &DevcontainerConfig,
+    project: Option<&serde_json::Value>,
+) -> eyre::Result<Option<Vec<String>>> {
+    let requested: Option<Vec<&str>> = config.run_services.as_ref().map(|services| {
+        services
+            .iter()
+            .chain(std::iter::once(&config.service))
+            .map(String::as_str)
+            .unique()
+            .collect()
+    });
+    let owned = |services: Vec<&str>| services.into_iter().map(str::to_owned).collect();
+
+    let Some(project) = project else {
+        return Ok(requested.map(owned));
+    };
+    let services = &project["services"];
+    let mut available: Vec<&str> = services
+        .as_object()
+        .map(|s| s.keys().map(String::as_str).collect())
+        .unwrap_or_default();
+    available.sort_unstable();
+
+    let missing = missing_services(config, &available);
+    if missing.is_empty() {
+        return Ok(requested.map(|requested| {
+            let all = with_dependencies(services, &requested);
+            if all.len() > requested.len() {
+                tracing::info!(
+                    "also starting dependencies: {}",
+                    all[requested.len()..].join(", ")
+                );
+            }
+            all
+        }));
+    }
+    Err(eyre!(
+        "{} not defined in the compose files",
+        missing.iter().map(|s| format!("`{s}`")).join(", ")
+    ))
+    .suggestion(format!("available services: {}", available.join(", ")))
+}
+
+/// `requested`, followed by every service they transitively `depends_on`, other than those
+/// marked `required: false`, in `services` from `docker compose config`.
+fn with_dependencies(services: &serde_json::Value, requested: &[&str]) -> Vec<String> {
+    let mut all: Vec<String> = requested.iter().map(|&s| s.to_owned()).collect();
+    let mut i = 0;
+    while i < all.len() {
+        let deps: Vec<&str> = match &services[&all[i]]["depends_on"] {
+            serde_json::Value::Object(deps) => deps
+                .iter()
+                .filter(|(_, dep)| dep["required"] != false)
+                .map(|(name, _)| name.as_str())
+                .collect(),
+            serde_json::Value::Array(deps) => {
+                deps.iter().filter_map(serde_json::Value::as_str).collect()
+            }
+            _ => Vec::new(),
+        };
+        for dep in deps {
+            if !all.iter().any(|s| s == dep) {
+                all.push(dep.to_owned());
+            }
+        }
+        i += 1;
+    }
+    all
+}
+
+/// The configured `service` and `runServices` missing from `available`.
+fn missing_services<'a>(config: &'a DevcontainerConfig, available: &[&str]) -> Vec<&'a str> {
+    let run_services = config.run_services.iter().flatten();
+    std::iter::once(&config.service)
+        .chain(run_services)
+        .map(String::as_str)
+        .filter(|s| !available.contains(s))
+        .unique()
+        .collect()
 }
 
 pub(crate) async fn compose_ps_q(
@@ -64,6 +494,203 @@ pub(crate) async fn compose_ps_q(
     Ok(id)
 }
 
+/// Rendered `containerEnv`, as written to the override.
+fn container_env(
+    devcontainer: &DevcontainerState,
+    context: &substitution::Context<'_>,
+) -> IndexMap<String, String> {
+    devcontainer
+        .config
+        .container_env
+        .iter()
+        .map(|(k, v)| (k.clone(), v.render(context)))
+        .collect()
+}
+
+/// Describe how the running primary container differs from what devcontainer.json asks for.
+///
+/// Only the settings we put in the override are compared (`containerEnv`, `containerUser`,
+/// `init`, `privileged`); an empty result means the container can be reused as is.
+pub(crate) fn config_drift(
+    devcontainer: &DevcontainerState,
+    workspace: &Workspace,
+    details: &docker::ContainerDetails,
+) -> Vec<String> {
+    let config = &devcontainer.config;
+    let context = substitution::Context::new(&workspace.path, &config.workspace_folder);
+    let actual_env = details.config.parsed_env();
+
+    // Values are left out; containerEnv is a common place for tokens.
+    let mut drift: Vec<String> = container_env(devcontainer, &context)
+        .into_iter()
+        .filter(|(key, value)| actual_env.get(key) != Some(value))
+        .map(|(key, _)| format!("containerEnv.{key} changed"))
+        .collect();
+
+    if let Some(user) = &config.container_user
+        && details.config.user != *user
+    {
+        drift.push(format!(
+            "containerUser: {:?} -> {user:?}",
+            details.config.user
+        ));
+    }
+    if let Some(init) = config.init
+        && details.host_config.init.unwrap_or(false) != init
+    {
+        drift.push(format!("init: {} -> {init}", !init));
+    }
+    if let Some(privileged) = config.privileged
+        && details.host_config.privileged != privileged
+    {
+        drift.push(format!("privileged: {} -> {privileged}", !privileged));
+    }
+    drift
+}
+
+/// The labels we put on the primary container.
+fn labels(devcontainer: &DevcontainerState, workspace: &Workspace) -> Vec<(String, String)> {
+    let mut labels = vec![
+        (
+            LOCAL_FOLDER_LABEL.to_owned(),
+            workspace.path.display().to_string(),
+        ),
+        (
+            MANAGED_LABEL.to_owned(),
+            workspace.state.config.managed_label_value().to_owned(),
+        ),
+        (
+            PROJECT_LABEL.to_owned(),
+            workspace.state.project_name.to_string(),
+        ),
+        (WORKSPACE_LABEL.to_owned(), workspace.name.clone()),
+    ];
+    if let Some(path) = &devcontainer.path {
+        labels.push((
+            "devcontainer.config_file".to_owned(),
+            path.display().to_string(),
+        ));
+    }
+    labels
+}
+
+/// Settings the reference CLI records in the `devcontainer.metadata` label; editors attaching to
+/// a running container read them from there rather than from devcontainer.json.
+const METADATA_KEYS: [&str; 16] = [
+    "containerUser",
+    "remoteUser",
+    "remoteEnv",
+    "updateRemoteUserUID",
+    "userEnvProbe",
+    "overrideCommand",
+    "shutdownAction",
+    "forwardPorts",
+    "portsAttributes",
+    "onCreateCommand",
+    "updateContentCommand",
+    "postCreateCommand",
+    "postStartCommand",
+    "postAttachCommand",
+    "waitFor",
+    "customizations",
+];
+
+/// The `devcontainer.metadata` label: a JSON array with one entry, holding the set
+/// [`METADATA_KEYS`] of `config`.
+fn metadata_label(config: &DevcontainerConfig) -> eyre::Result<String> {
+    let serde_json::Value::Object(mut all) = serde_json::to_value(config)? else {
+        unreachable!("config serializes to an object");
+    };
+    let entry: serde_json::Map<_, _> = METADATA_KEYS
+        .into_iter()
+        .filter_map(|key| Some((key.to_owned(), all.remove(key)?)))
+        .filter(|(_, value)| !value.is_null())
+        .collect();
+    Ok(serde_json::to_string(&[entry])?)
+}
+
+/// Compose `volumes` entries for the primary container: `mounts`, plus the git mounts.
+fn volumes(
+    devcontainer: &DevcontainerState,
+    workspace: &Workspace,
+    context: &substitution::Context<'_>,
+) -> eyre::Result<Vec<String>> {
+    let mut volumes: Vec<String> = devcontainer
+        .config
+        .mounts
+        .iter()
+        .map(|entry| entry.to_compose_volume(context))
+        .collect::<eyre::Result<_>>()?;
+    if devcontainer.devconcurrent().mount_git() && !workspace.is_root {
+        // Git worktrees store a tiny `.git` file pointing to the real `.git` dir at the project
+        // root; mount the real dir at its original path so `git` works inside the container.
+        let git_dir = workspace.state.project.path.join(".git");
+        let git_dir = git_dir.display();
+        volumes.push(format!("{git_dir}:{git_dir}"));
+
+        // We also need to mount the workspace at the git-aware path so that certain git commands
+        // can find it (such as `git --git-dir=...`).
+        let ws_dir = workspace.path.display();
+        volumes.push(format!("{ws_dir}:{ws_dir}"));
+    }
+    Ok(volumes)
+}
+
+/// Describe every way the primary container differs from devcontainer.json, for `dc diff`:
+/// [`config_drift`], plus labels and mounts.
+pub(crate) fn container_diff(
+    devcontainer: &DevcontainerState,
+    workspace: &Workspace,
+    details: &docker::ContainerDetails,
+) -> eyre::Result<Vec<String>> {
+    let context =
+        substitution::Context::new(&workspace.path, &devcontainer.config.workspace_folder);
+    let mut diff = config_drift(devcontainer, workspace, details);
+    for (key, value) in labels(devcontainer, workspace) {
+        match details.config.labels.get(&key) {
+            None => diff.push(format!("label {key}: missing")),
+            Some(actual) if *actual != value => {
+                diff.push(format!("label {key}: {actual:?} -> {value:?}"));
+            }
+            Some(_) => {}
+        }
+    }
+    diff.extend(mount_drift(
+        &volumes(devcontainer, workspace, &context)?,
+        &details.mounts,
+    ));
+    Ok(diff)
+}
+
+/// Compare compose `volumes` entries (`source:target[:mode]`, or just `target` for an anonymous
+/// volume) against the container's mounts, by target.
+fn mount_drift(volumes: &[String], mounts: &[docker::MountPoint]) -> Vec<String> {
+    let mut drift = Vec::new();
+    for volume in volumes {
+        let mut parts = volume.split(':');
+        let (source, target) = match (parts.next(), parts.next()) {
+            (Some(source), Some(target)) => (Some(source), target),
+            (Some(target), None) => (None, target),
+            _ => continue,
+        };
+        let Some(mount) = mounts.iter().find(|m| m.destination == target) else {
+            drift.push(format!("mount {target}: missing"));
+            continue;
+        };
+        let Some(source) = source else {
+            continue;
+        };
+        let actual = match &mount.name {
+            Some(name) => name,
+            None => &mount.source,
+        };
+        if actual != source {
+            drift.push(format!("mount {target}: {actual} -> {source}"));
+        }
+    }
+    drift
+}
+
 /// Generate a compose override file
 ///
 /// We set the standard devcontainer labels, our own labels, and any appropriate overrides from
@@ -71,30 +698,46 @@ pub(crate) async fn compose_ps_q(
 fn write_compose_override(
     devcontainer: &DevcontainerState,
     workspace: &Workspace,
+    publish: bool,
 ) -> eyre::Result<PathBuf> {
-    let override_path = override_path(workspace);
+    let override_path = override_path(workspace)?;
+    let content = compose_override(devcontainer, workspace, publish)?;
+    write_json(&override_path, &content)?;
+    Ok(override_path)
+}
 
-    let mut labels = vec![
-        format!("{}={}", LOCAL_FOLDER_LABEL, workspace.path.display()),
-        format!("{}=true", MANAGED_LABEL),
-        format!("{}={}", PROJECT_LABEL, workspace.state.project_name),
-        format!("{}={}", WORKSPACE_LABEL, workspace.name),
-    ];
-    if let Some(path) = &devcontainer.path {
-        labels.push(format!("devcontainer.config_file={}", path.display()));
+/// Write `content` to `path`, as JSON (which compose reads as YAML), creating its directory.
+fn write_json(path: &Path, content: &serde_json::Value) -> eyre::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .wrap_err_with(|| format!("failed to create {}", dir.display()))?;
     }
+    std::fs::write(path, serde_json::to_string_pretty(content)?)
+        .wrap_err_with(|| format!("failed to write {}", path.display()))
+}
+
+/// The compose override for the workspace's primary service, as [`write_compose_override`]
+/// writes it, publishing `forwardPorts` if `publish` is set.
+pub(crate) fn compose_override(
+    devcontainer: &DevcontainerState,
+    workspace: &Workspace,
+    publish: bool,
+) -> eyre::Result<serde_json::Value> {
+    let mut labels: Vec<String> = labels(devcontainer, workspace)
+        .into_iter()
+        .map(|(k, v)| format!("{k}={v}"))
+        .collect();
+    // Left out of `labels`, and so `dc diff`: it changes with any of its settings. `$$` keeps
+    // compose from interpolating `${...}` variables in it.
+    let metadata = metadata_label(&devcontainer.config)?.replace('$', "$$");
+    labels.push(format!("devcontainer.metadata={metadata}"));
     let mut service_obj = json!({
         "labels": labels
     });
 
     let context =
         substitution::Context::new(&workspace.path, &devcontainer.config.workspace_folder);
-    let env: indexmap::IndexMap<String, String> = devcontainer
-        .config
-        .container_env
-        .iter()
-        .map(|(k, v)| (k.clone(), v.render(&context)))
-        .collect();
+    let env = container_env(devcontainer, &context);
     if !env.is_empty() {
         service_obj["environment"] = json!(env);
     }
@@ -111,55 +754,232 @@ fn write_compose_override(
     if !devcontainer.config.security_opt.is_empty() {
         service_obj["security_opt"] = json!(devcontainer.config.security_opt);
     }
+    if !devcontainer.config.app_port.is_empty() {
+        let ports: Vec<String> = devcontainer
+            .config
+            .app_port
+            .iter()
+            .map(|p| p.to_compose_port())
+            .collect();
+        service_obj["ports"] = json!(ports);
+    }
     if let Some(ref user) = devcontainer.config.container_user {
         service_obj["user"] = json!(user);
     }
 
-    let devconcurrent_options = devcontainer.devconcurrent();
-
-    let mut volumes: Vec<String> = devcontainer
-        .config
-        .mounts
-        .iter()
-        .map(|entry| entry.to_compose_volume(&context))
-        .collect::<eyre::Result<_>>()?;
-    if devconcurrent_options.mount_git() && !workspace.is_root {
-        // Git worktrees store a tiny `.git` file pointing to the real `.git` dir at the project
-        // root; mount the real dir at its original path so `git` works inside the container.
-        let git_dir = workspace.state.project.path.join(".git");
-        let git_dir = git_dir.display();
-        volumes.push(format!("{git_dir}:{git_dir}"));
-
-        // We also need to mount the workspace at the git-aware path so that certain git commands
-        // can find it (such as `git --git-dir=...`).
-        let ws_dir = workspace.path.display();
-        volumes.push(format!("{ws_dir}:{ws_dir}"));
+    for arg in run_args::apply(&devcontainer.config.run_args, &mut service_obj) {
+        tracing::warn!("`runArgs` entry `{arg}` has no compose equivalent; ignoring");
     }
+
+    let volumes = volumes(devcontainer, workspace, &context)?;
     if !volumes.is_empty() {
         service_obj["volumes"] = json!(volumes);
     }
 
     if devcontainer.config.override_command {
-        // I believe this is the reference devcontainer overrideCommand.
+        // I believe this is the reference devcontainer overrideCommand, but for the idling.
+        let keep_alive = devcontainer.devconcurrent().keep_alive.script();
         service_obj["entrypoint"] = json!([
             "/bin/sh",
             "-c",
-            r#"echo Container started
+            format!(
+                r#"echo Container started
  trap "exit 0" 15
 
  exec "$@"
- while sleep 1 & wait $!; do :; done"#,
+ {keep_alive}"#
+            ),
             "-"
         ]);
         service_obj["command"] = json!([]);
     }
 
-    let content = serde_json::to_string_pretty(&json!({
-        "services": { &devcontainer.config.service: service_obj }
-    }))?;
+    let mut services = json!({ &devcontainer.config.service: service_obj });
+    if publish {
+        add_published_ports(
+            &mut services,
+            &devcontainer.config.service,
+            &devcontainer.config.forward_ports,
+        );
+    }
+    Ok(json!({ "services": services }))
+}
 
-    workspace.state.ensure_project_working_dir()?;
-    std::fs::write(&override_path, content)
-        .wrap_err_with(|| format!("failed to write {}", override_path.display()))?;
-    Ok(override_path)
+/// Publish `forwardPorts` in the override's `services`, as `dc up --publish` does: each on the
+/// same host port, from its service (the primary one, unless it names another).
+fn add_published_ports(services: &mut serde_json::Value, primary: &str, ports: &[ForwardPort]) {
+    for port in ports {
+        let service = port.service.as_deref().unwrap_or(primary);
+        let published = &mut services[service]["ports"];
+        if published.is_null() {
+            *published = json!([]);
+        }
+        if let Some(published) = published.as_array_mut() {
+            published.push(json!(format!("{0}:{0}", port.port)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn published_ports_go_on_their_service() {
+        let mut services = json!({ "app": { "ports": ["8000:8000"] } });
+        let ports: Vec<ForwardPort> = serde_json::from_value(json!([3000, "db:5432"])).unwrap();
+        add_published_ports(&mut services, "app", &ports);
+        assert_eq!(
+            services,
+            json!({
+                "app": { "ports": ["8000:8000", "3000:3000"] },
+                "db": { "ports": ["5432:5432"] },
+            })
+        );
+    }
+
+    #[test]
+    fn dependencies_are_added_transitively() {
+        let services = json!({
+            "app": { "depends_on": { "api": { "condition": "service_started" } } },
+            "api": { "depends_on": {
+                "db": { "condition": "service_healthy", "required": true },
+                "metrics": { "condition": "service_started", "required": false },
+            } },
+            "worker": { "depends_on": ["db", "cache"] },
+            "db": {},
+            "cache": {},
+            "metrics": {},
+        });
+        assert_eq!(
+            with_dependencies(&services, &["worker", "app"]),
+            ["worker", "app", "db", "cache", "api"]
+        );
+        assert_eq!(with_dependencies(&services, &["db"]), ["db"]);
+    }
+
+    #[test]
+    fn missing_services_checks_service_and_run_services() {
+        let config: DevcontainerConfig =
+            serde_json::from_value(json!({ "service": "app", "runServices": ["db", "cache"] }))
+                .unwrap();
+        assert_eq!(missing_services(&config, &["app", "db"]), ["cache"]);
+        assert_eq!(missing_services(&config, &["cache"]), ["app", "db"]);
+
+        let config: DevcontainerConfig =
+            serde_json::from_value(json!({ "service": "app", "runServices": ["app"] })).unwrap();
+        assert_eq!(missing_services(&config, &[]), ["app"]);
+    }
+
+    #[test]
+    fn compose_files_are_relative_to_devcontainer_json() {
+        let dir = Path::new("/code/app/.devcontainer");
+        assert_eq!(
+            resolve_compose_file(dir, "../docker-compose.yml"),
+            Path::new("/code/app/docker-compose.yml")
+        );
+        assert_eq!(
+            resolve_compose_file(dir, "./compose.yml"),
+            Path::new("/code/app/.devcontainer/compose.yml")
+        );
+        assert_eq!(
+            resolve_compose_file(Path::new("/code/app"), "docker-compose.yml"),
+            Path::new("/code/app/docker-compose.yml")
+        );
+        assert_eq!(
+            resolve_compose_file(dir, "/etc/compose.yml"),
+            Path::new("/etc/compose.yml")
+        );
+    }
+
+    #[test]
+    fn single_container_compose_builds_and_mounts_the_workspace() {
+        let config: DevcontainerConfig = serde_json::from_value(json!({
+            "service": "app",
+            "workspaceFolder": "/workspaces/app",
+            "build": { "dockerfile": "Dockerfile", "context": "..", "args": { "RUST": "1.90" } },
+        }))
+        .unwrap();
+        let dir = Path::new("/code/app/.devcontainer");
+        assert_eq!(
+            single_container_compose(&config, dir, Path::new("/code/app")).unwrap(),
+            json!({ "services": { "app": {
+                "build": {
+                    "context": "/code/app",
+                    "dockerfile": "/code/app/.devcontainer/Dockerfile",
+                    "args": { "RUST": "1.90" },
+                },
+                "volumes": ["/code/app:/workspaces/app"],
+                "working_dir": "/workspaces/app",
+            } } })
+        );
+
+        let config: DevcontainerConfig = serde_json::from_value(json!({
+            "service": "app",
+            "image": "rust:1",
+            "workspaceFolder": "/src",
+            "workspaceMount": "source=${localWorkspaceFolder},target=/src,type=bind",
+        }))
+        .unwrap();
+        assert_eq!(
+            single_container_compose(&config, dir, Path::new("/code/wt")).unwrap(),
+            json!({ "services": { "app": {
+                "image": "rust:1",
+                "volumes": ["/code/wt:/src"],
+                "working_dir": "/src",
+            } } })
+        );
+    }
+
+    #[test]
+    fn metadata_label_has_only_metadata_keys() {
+        let config: DevcontainerConfig = serde_json::from_value(json!({
+            "service": "app",
+            "remoteUser": "node",
+            "postStartCommand": "npm start",
+        }))
+        .unwrap();
+        let label: serde_json::Value =
+            serde_json::from_str(&metadata_label(&config).unwrap()).unwrap();
+        let entry = &label[0];
+        assert_eq!(entry["remoteUser"], "node");
+        assert_eq!(entry["postStartCommand"], "npm start");
+        assert!(entry.get("service").is_none());
+        assert!(entry.get("containerUser").is_none());
+    }
+
+    fn mount(name: Option<&str>, source: &str, destination: &str) -> docker::MountPoint {
+        serde_json::from_value(json!({
+            "Name": name,
+            "Source": source,
+            "Destination": destination,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn mount_drift_compares_by_target() {
+        let volumes = [
+            "/home/me/.aws:/aws:ro".to_owned(),
+            "cache:/cache".to_owned(),
+            "/anon".to_owned(),
+            "/home/me/.ssh:/ssh".to_owned(),
+        ];
+        let mounts = [
+            mount(None, "/home/other/.aws", "/aws"),
+            mount(
+                Some("cache"),
+                "/var/lib/docker/volumes/cache/_data",
+                "/cache",
+            ),
+            mount(Some("3f2a"), "/var/lib/docker/volumes/3f2a/_data", "/anon"),
+        ];
+        assert_eq!(
+            mount_drift(&volumes, &mounts),
+            [
+                "mount /aws: /home/other/.aws -> /home/me/.aws",
+                "mount /ssh: missing",
+            ]
+        );
+    }
 }
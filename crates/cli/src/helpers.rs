@@ -1,5 +1,7 @@
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+use eyre::{WrapErr, eyre};
 
 use serde::Deserialize;
 
@@ -22,6 +24,40 @@ pub(crate) fn forward_to_shell(command: &str) -> eyre::Result<()> {
     Ok(())
 }
 
+/// Match a workspace name typed by the user against existing ones: an exact match, then a unique
+/// prefix, then a unique fuzzy match (the typed characters in order, e.g. `fxlg` for
+/// `fix-login`).
+///
+/// Returns `Ok(None)` when nothing matches, and an error listing the candidates when the match is
+/// ambiguous.
+pub(crate) fn resolve_workspace_name<'a>(
+    name: &str,
+    names: &[&'a str],
+) -> Result<Option<&'a str>, String> {
+    if let Some(exact) = names.iter().find(|n| **n == name) {
+        return Ok(Some(exact));
+    }
+    let is_subsequence = |candidate: &str| {
+        let mut chars = candidate.chars();
+        name.chars().all(|c| chars.any(|d| d == c))
+    };
+    let matchers: [&dyn Fn(&str) -> bool; 2] = [&|n| n.starts_with(name), &is_subsequence];
+    for matches in matchers {
+        let found: Vec<&str> = names.iter().copied().filter(|n| matches(n)).collect();
+        match found[..] {
+            [] => {}
+            [one] => return Ok(Some(one)),
+            _ => {
+                return Err(format!(
+                    "'{name}' matches several workspaces: {}",
+                    found.join(", ")
+                ));
+            }
+        }
+    }
+    Ok(None)
+}
+
 /// Simple validator for workspace and project names.
 ///
 /// We use the same rules for both for simplicity.
@@ -51,3 +87,64 @@ pub(crate) fn deserialize_shell_path<'de, D: serde::Deserializer<'de>>(
     let s = String::deserialize(d)?;
     Ok(PathBuf::from(shellexpand::tilde(&s).as_ref()))
 }
+
+/// Read an env file: `KEY=VALUE` per line, skipping blank lines and `#` comments.
+pub(crate) fn read_env_file(path: &Path) -> eyre::Result<Vec<(String, String)>> {
+    let contents = std::fs::read_to_string(path)
+        .wrap_err_with(|| format!("failed to read env file {}", path.display()))?;
+    parse_env_file(&contents).wrap_err_with(|| format!("failed to parse {}", path.display()))
+}
+
+fn parse_env_file(contents: &str) -> eyre::Result<Vec<(String, String)>> {
+    contents
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(n, line)| {
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| eyre!("line {n}: expected KEY=VALUE"))?;
+            let key = key.trim();
+            eyre::ensure!(!key.is_empty(), "line {n}: empty variable name");
+            Ok((key.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn env_file_skips_comments_and_blanks() {
+        let parsed = parse_env_file("# secrets\n\nFOO=bar\n  BAZ=a=b\nEMPTY=\n").unwrap();
+        assert_eq!(
+            parsed,
+            [
+                ("FOO".to_string(), "bar".to_string()),
+                ("BAZ".to_string(), "a=b".to_string()),
+                ("EMPTY".to_string(), String::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn workspace_name_prefers_exact_then_prefix_then_fuzzy() {
+        let names = ["api", "api-v2", "fix-login", "feature-x"];
+        assert_eq!(resolve_workspace_name("api", &names), Ok(Some("api")));
+        assert_eq!(resolve_workspace_name("api-", &names), Ok(Some("api-v2")));
+        assert_eq!(
+            resolve_workspace_name("fxlg", &names),
+            Ok(Some("fix-login"))
+        );
+        assert_eq!(resolve_workspace_name("zzz", &names), Ok(None));
+        assert!(resolve_workspace_name("f", &names).is_err());
+    }
+
+    #[test]
+    fn env_file_rejects_malformed_lines() {
+        assert!(parse_env_file("FOO").is_err());
+        assert!(parse_env_file("=bar").is_err());
+    }
+}
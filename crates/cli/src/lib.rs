@@ -7,7 +7,7 @@ use color_eyre::config::HookBuilder;
 use eyre::eyre;
 
 use crate::cli::Cli;
-use crate::subscriber::init_subscriber;
+use crate::subscriber::{Progress, init_subscriber};
 
 mod ansi;
 mod bytes;
@@ -17,6 +17,9 @@ pub mod config;
 pub mod devcontainer;
 mod docker;
 mod helpers;
+mod paths;
+mod preflight;
+mod redact;
 pub mod run;
 mod state;
 mod subscriber;
@@ -28,7 +31,6 @@ pub async fn cli_main() -> eyre::Result<()> {
     HookBuilder::default()
         .display_env_section(false)
         .install()?;
-    init_subscriber();
 
     let shell_str = std::env::var("COMPLETE").ok();
 
@@ -40,6 +42,7 @@ pub async fn cli_main() -> eyre::Result<()> {
         .unwrap_or_else(|e| e.exit());
 
     if completion {
+        init_subscriber(Progress::Human, false);
         // When completion is triggered with no arguments, we're running the
         // initial shell registration.
         if std::env::args_os().len() == 1 {
@@ -63,10 +66,11 @@ pub async fn cli_main() -> eyre::Result<()> {
             std::process::exit(e.exit_code());
         }
     };
+    init_subscriber(cli.progress, cli.quiet);
     cli.run().await
 }
 
-fn register_shell_function(shell_str: &str) -> eyre::Result<()> {
+pub(crate) fn register_shell_function(shell_str: &str) -> eyre::Result<()> {
     let shell = shell_str.parse::<Shell>().map_err(|e| eyre!("{e}"))?;
     let function = shell_function(shell)?;
     println!("{function}");
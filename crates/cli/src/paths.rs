@@ -0,0 +1,35 @@
+//! Where devconcurrent keeps its own files, so they all end up somewhere predictable that the
+//! user can move.
+
+use std::path::PathBuf;
+
+use directories::ProjectDirs;
+use eyre::OptionExt;
+
+/// Overrides [`state_dir`].
+pub(crate) const STATE_DIR_ENV: &str = "DC_STATE_DIR";
+
+fn dirs() -> eyre::Result<ProjectDirs> {
+    ProjectDirs::from("", "", "devconcurrent").ok_or_eyre("could not determine home directory")
+}
+
+/// Where `config.toml` lives: `$XDG_CONFIG_HOME/devconcurrent` or similar.
+pub(crate) fn config_dir() -> eyre::Result<PathBuf> {
+    Ok(dirs()?.config_dir().to_owned())
+}
+
+/// The default parent of each project's worktrees: `$XDG_DATA_HOME/devconcurrent` or similar.
+pub(crate) fn data_dir() -> eyre::Result<PathBuf> {
+    Ok(dirs()?.data_dir().to_owned())
+}
+
+/// Files we generate and manage ourselves, like compose overrides: `$DC_STATE_DIR`, else
+/// `$XDG_STATE_HOME/devconcurrent`. Platforms without a state directory use the local data
+/// directory.
+pub(crate) fn state_dir() -> eyre::Result<PathBuf> {
+    if let Some(dir) = std::env::var_os(STATE_DIR_ENV).filter(|d| !d.is_empty()) {
+        return Ok(dir.into());
+    }
+    let dirs = dirs()?;
+    Ok(dirs.state_dir().unwrap_or(dirs.data_local_dir()).to_owned())
+}
@@ -0,0 +1,194 @@
+//! Checks for the tools we shell out to (Docker, git), so a broken setup is reported with what to
+//! do about it rather than as a confusing failure halfway through a command.
+
+use std::path::Path;
+use std::sync::OnceLock;
+
+use color_eyre::Section;
+use eyre::eyre;
+use tokio::process::Command;
+
+use crate::docker::{DockerClient, compose};
+
+/// Connect to the daemon and make sure the `docker compose` plugin is available.
+///
+/// A missing `buildx` only warns: compose falls back to the legacy builder without it.
+pub(crate) async fn check(docker: &DockerClient) -> eyre::Result<()> {
+    if let Err(report) = docker.client().await {
+        let suggestion = report
+            .chain()
+            .find_map(|e| e.downcast_ref::<docker::Error>())
+            .and_then(connect_suggestion);
+        return Err(match suggestion {
+            Some(suggestion) => report.suggestion(suggestion),
+            None => report,
+        });
+    }
+
+    let (compose, buildx) = tokio::join!(
+        succeeds(&["compose", "version"]),
+        succeeds(&["buildx", "version"])
+    );
+    if !compose {
+        return Err(eyre!("`docker compose` is not available").suggestion(
+            "install the Docker Compose plugin: https://docs.docker.com/compose/install/",
+        ));
+    }
+    for var in compose::IGNORED_ENV {
+        if std::env::var_os(var).is_some() {
+            tracing::warn!("{var} is set, but devconcurrent ignores it for its compose commands");
+        }
+    }
+    if !buildx {
+        tracing::warn!(
+            "`docker buildx` is not available; builds will use the legacy builder. \
+             See https://docs.docker.com/go/buildx/"
+        );
+    }
+    Ok(())
+}
+
+/// The oldest git with every `git worktree` subcommand we use (`remove` arrived in 2.17).
+const MIN_GIT: (u32, u32) = (2, 17);
+
+/// Make sure a new enough `git` is installed. Only checked once per process.
+pub(crate) fn check_git() -> eyre::Result<()> {
+    static RESULT: OnceLock<Result<(), String>> = OnceLock::new();
+    let result = RESULT.get_or_init(|| {
+        let out = std::process::Command::new("git")
+            .arg("--version")
+            .output()
+            .map_err(|e| format!("could not run `git`: {e}"))?;
+        let version = String::from_utf8_lossy(&out.stdout);
+        match parse_git_version(&version) {
+            Some(found) if found >= MIN_GIT => Ok(()),
+            Some((major, minor)) => Err(format!(
+                "git {major}.{minor} is too old; devconcurrent needs git {}.{} or newer",
+                MIN_GIT.0, MIN_GIT.1
+            )),
+            None => Err(format!(
+                "unrecognized `git --version` output: {}",
+                version.trim()
+            )),
+        }
+    });
+    result.clone().map_err(|e| {
+        eyre!(e).suggestion(format!(
+            "install git {}.{} or newer: https://git-scm.com/downloads",
+            MIN_GIT.0, MIN_GIT.1
+        ))
+    })
+}
+
+/// Warn if `dir` is on a filesystem that's cleared on reboot, like a tmpfs `/tmp`: anything not
+/// pushed from a worktree there would be lost. Only Linux is checked.
+pub(crate) fn warn_if_volatile(dir: &Path) {
+    let Ok(mounts) = std::fs::read_to_string("/proc/self/mounts") else {
+        return;
+    };
+    let Ok(dir) = dir.canonicalize() else {
+        return;
+    };
+    if let Some(fs @ ("tmpfs" | "ramfs")) = mount_fs_type(&mounts, &dir) {
+        tracing::warn!(
+            "{} is on a {fs}, so its worktrees are lost on reboot; set `worktreeFolder` to a \
+             persistent directory",
+            dir.display()
+        );
+    }
+}
+
+/// The filesystem type of the mount `path` is on, from `/proc/self/mounts`-style `mounts`.
+fn mount_fs_type<'a>(mounts: &'a str, path: &Path) -> Option<&'a str> {
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split(' ');
+            let _device = fields.next()?;
+            // Spaces in mount points are escaped.
+            let mount_point = fields.next()?.replace("\\040", " ");
+            let fs = fields.next()?;
+            path.starts_with(&mount_point)
+                .then_some((mount_point.len(), fs))
+        })
+        // Later mounts shadow earlier ones on the same point.
+        .max_by_key(|&(len, _)| len)
+        .map(|(_, fs)| fs)
+}
+
+/// Parse `git version 2.39.3 (Apple Git-145)` and the like into `(major, minor)`.
+fn parse_git_version(output: &str) -> Option<(u32, u32)> {
+    let version = output.trim().strip_prefix("git version ")?;
+    let mut parts = version.split(['.', ' ']);
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next()?.parse().ok()?;
+    Some((major, minor))
+}
+
+fn connect_suggestion(error: &docker::Error) -> Option<&'static str> {
+    match error {
+        docker::Error::SocketNotFound { .. } => Some(
+            "install and start Docker (https://docs.docker.com/engine/install/) or Podman, or set \
+             DOCKER_HOST to the socket of a running daemon",
+        ),
+        docker::Error::NonUnixHost { .. } => {
+            Some("only unix sockets are supported; set DOCKER_HOST to `unix:///path/to/socket`")
+        }
+        docker::Error::Transport { .. } => {
+            Some("the Docker daemon isn't responding; make sure it is running")
+        }
+        docker::Error::IncompatibleApiVersion { .. } => Some("upgrade Docker to a newer release"),
+        _ => None,
+    }
+}
+
+/// Whether `docker <args>` runs successfully.
+async fn succeeds(args: &[&str]) -> bool {
+    Command::new("docker")
+        .args(args)
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .await
+        .is_ok_and(|status| status.success())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_socket_suggests_installing_docker() {
+        let error = docker::Error::SocketNotFound { tried: Vec::new() };
+        assert!(connect_suggestion(&error).is_some_and(|s| s.contains("install")));
+        assert_eq!(connect_suggestion(&docker::Error::NotFound), None);
+    }
+
+    #[test]
+    fn finds_the_innermost_mount() {
+        let mounts = "/dev/sda1 / ext4 rw 0 0\n\
+                      tmpfs /tmp tmpfs rw 0 0\n\
+                      /dev/sdb1 /tmp/my\\040disk xfs rw 0 0\n";
+        assert_eq!(mount_fs_type(mounts, Path::new("/home/me")), Some("ext4"));
+        assert_eq!(mount_fs_type(mounts, Path::new("/tmp/wt")), Some("tmpfs"));
+        assert_eq!(mount_fs_type(mounts, Path::new("/tmpfoo")), Some("ext4"));
+        assert_eq!(
+            mount_fs_type(mounts, Path::new("/tmp/my disk/wt")),
+            Some("xfs")
+        );
+    }
+
+    #[test]
+    fn parses_git_versions() {
+        assert_eq!(parse_git_version("git version 2.43.0\n"), Some((2, 43)));
+        assert_eq!(
+            parse_git_version("git version 2.39.3 (Apple Git-145)"),
+            Some((2, 39))
+        );
+        assert_eq!(
+            parse_git_version("git version 2.41.0.windows.1"),
+            Some((2, 41))
+        );
+        assert_eq!(parse_git_version("hub version 2.14.2"), None);
+    }
+}
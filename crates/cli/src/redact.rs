@@ -0,0 +1,75 @@
+//! Masking of secret values in our log output.
+//!
+//! Lifecycle command output is forwarded verbatim, so a script that echoes a
+//! token would otherwise leak it into `dc`'s output (and any CI logs capturing
+//! it).
+
+use std::borrow::Cow;
+use std::sync::RwLock;
+
+/// Comma-separated names of host environment variables whose values to mask.
+pub(crate) const REDACT_ENV: &str = "DC_REDACT";
+
+const MASK: &str = "***";
+
+static SECRETS: RwLock<Vec<String>> = RwLock::new(Vec::new());
+
+/// Mask `value` wherever it appears in log output from now on.
+pub(crate) fn register(value: &str) {
+    if value.is_empty() {
+        return;
+    }
+    let mut secrets = SECRETS.write().unwrap();
+    if !secrets.iter().any(|s| s == value) {
+        secrets.push(value.to_string());
+        // Longest first, so a secret containing another is masked whole.
+        secrets.sort_by_key(|s| std::cmp::Reverse(s.len()));
+    }
+}
+
+/// Register the values of the variables named in `DC_REDACT`.
+pub(crate) fn register_from_env() {
+    let Ok(names) = std::env::var(REDACT_ENV) else {
+        return;
+    };
+    for name in names.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+        if let Ok(value) = std::env::var(name) {
+            register(&value);
+        }
+    }
+}
+
+/// Replace every registered secret in `s` with `***`.
+pub(crate) fn redact(s: &str) -> Cow<'_, str> {
+    let secrets = SECRETS.read().unwrap();
+    let mut out = Cow::Borrowed(s);
+    for secret in secrets.iter() {
+        if out.contains(secret.as_str()) {
+            out = Cow::Owned(out.replace(secret.as_str(), MASK));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn masks_registered_values() {
+        register("hunter2-redact-test");
+        register("hunter2-redact-test-longer");
+        assert_eq!(
+            redact("token=hunter2-redact-test-longer, again hunter2-redact-test"),
+            "token=***, again ***"
+        );
+    }
+
+    #[test]
+    fn leaves_other_text_alone() {
+        assert!(matches!(
+            redact("nothing to see here"),
+            Cow::Borrowed("nothing to see here")
+        ));
+    }
+}
@@ -1,6 +1,9 @@
 use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::sync::Mutex;
 
 use color_eyre::owo_colors::OwoColorize;
+use color_eyre::{Section, SectionExt};
 use crossterm::style::SetForegroundColor;
 use eyre::WrapErr;
 use itertools::Itertools;
@@ -10,6 +13,7 @@ use tracing_indicatif::span_ext::IndicatifSpanExt;
 use tokio::io::AsyncBufReadExt;
 
 use crate::ansi::{BLUE, CYAN, GREEN, RESET, YELLOW};
+use crate::redact::redact;
 
 pub(crate) mod cmd;
 pub(crate) mod docker_exec;
@@ -22,6 +26,8 @@ pub(crate) struct Token(());
 
 const TOK: Token = Token(());
 const LABEL_COLORS: &[SetForegroundColor] = &[YELLOW, GREEN, BLUE, CYAN];
+/// How many lines of a failed command's output to show with its error.
+const OUTPUT_TAIL: usize = 20;
 
 pub(crate) trait Runnable: Sync {
     fn name(&self) -> Cow<'_, str>;
@@ -64,7 +70,6 @@ impl Runner {
         let names = runnables.iter().map(|r| r.name()).collect::<Vec<_>>();
         let description = names.join(", ");
         let span = run_span(name, &description);
-        let _enter = span.enter();
         let futures: Vec<_> = runnables
             .into_iter()
             .enumerate()
@@ -77,6 +82,7 @@ impl Runner {
                 let message = "Running".blue().to_string();
 
                 let span = info_span!(
+                    parent: &span,
                     "parallel",
                     indicatif.pb_show = true,
                     name,
@@ -90,7 +96,11 @@ impl Runner {
             })
             .collect();
 
-        futures::future::try_join_all(futures).await?;
+        // Instrumented rather than entered: an entered span would stay current on the thread
+        // while we're suspended, claiming other tasks' events.
+        futures::future::try_join_all(futures)
+            .instrument(span)
+            .await?;
 
         Ok(())
     }
@@ -98,6 +108,9 @@ impl Runner {
 
 /// Run the given command, capturing all of its output and printing it ourselves, so it plays nicely
 /// with our spinners.
+///
+/// Output is only shown at trace level, so if the command fails, its last lines go in the error;
+/// that's usually where a build says which step failed, and why.
 pub(crate) async fn run_command(mut cmd: tokio::process::Command) -> eyre::Result<()> {
     cmd.stdout(std::process::Stdio::piped());
     cmd.stderr(std::process::Stdio::piped());
@@ -107,16 +120,25 @@ pub(crate) async fn run_command(mut cmd: tokio::process::Command) -> eyre::Resul
     let mut stdout_lines = tokio::io::BufReader::new(child.stdout.take().unwrap()).lines();
     let mut stderr_lines = tokio::io::BufReader::new(child.stderr.take().unwrap()).lines();
 
+    let tail = Mutex::new(VecDeque::with_capacity(OUTPUT_TAIL));
+    let keep = |line: String| {
+        tracing::trace!("{line}");
+        let mut tail = tail.lock().expect("output lock poisoned");
+        if tail.len() == OUTPUT_TAIL {
+            tail.pop_front();
+        }
+        tail.push_back(line);
+    };
     let (status, (), ()) = tokio::join!(
         child.wait(),
         async {
             while let Ok(Some(line)) = stdout_lines.next_line().await {
-                tracing::trace!("{line}");
+                keep(line);
             }
         },
         async {
             while let Ok(Some(line)) = stderr_lines.next_line().await {
-                tracing::trace!("{line}");
+                keep(line);
             }
         },
     );
@@ -129,7 +151,13 @@ pub(crate) async fn run_command(mut cmd: tokio::process::Command) -> eyre::Resul
         let prog = cmd_std.get_program().display();
         let args = cmd_std.get_args().map(|a| a.display()).join(" ");
 
-        eyre::bail!("{prog} {args} exited with status {code}");
+        let err = eyre::eyre!("{prog} {args} exited with status {code}");
+        let tail = tail.into_inner().expect("output lock poisoned");
+        if tail.is_empty() {
+            return Err(err);
+        }
+        let output = redact(&tail.iter().join("\n")).into_owned();
+        return Err(err).with_section(|| output.header("Output:"));
     }
 
     Ok(())
@@ -145,3 +173,65 @@ pub(crate) async fn run_cmd(argv: &[&str], dir: Option<&std::path::Path>) -> eyr
 
     run_command(cmd).await
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tokio::sync::Notify;
+
+    use super::*;
+
+    /// Waits for `wait` (if any), then wakes `wake` (if any), then fails if told to.
+    struct Step {
+        name: &'static str,
+        wait: Option<Arc<Notify>>,
+        wake: Option<Arc<Notify>>,
+        fail: bool,
+    }
+
+    impl Runnable for Step {
+        fn name(&self) -> Cow<'_, str> {
+            self.name.into()
+        }
+
+        fn description(&self) -> Cow<'_, str> {
+            self.name.into()
+        }
+
+        async fn run(self, _: Token) -> eyre::Result<()> {
+            if let Some(wait) = self.wait {
+                wait.notified().await;
+            }
+            if let Some(wake) = self.wake {
+                wake.notify_one();
+            }
+            if self.fail {
+                eyre::bail!("failed");
+            }
+            Ok(())
+        }
+    }
+
+    // Both steps must make progress at once on the single thread `main` gives us.
+    #[tokio::test(flavor = "current_thread")]
+    async fn run_parallel_interleaves_on_one_thread() {
+        let notify = Arc::new(Notify::new());
+        let steps = [
+            Step {
+                name: "waiter",
+                wait: Some(notify.clone()),
+                wake: None,
+                fail: true,
+            },
+            Step {
+                name: "waker",
+                wait: None,
+                wake: Some(notify),
+                fail: false,
+            },
+        ];
+        let err = Runner::run_parallel("test", steps).await.unwrap_err();
+        assert_eq!(err.to_string(), "waiter");
+    }
+}
@@ -23,6 +23,15 @@ impl Cmd {
         }
     }
 
+    /// Like [`as_args`](Self::as_args), but a shell command runs in a login shell: bash if the
+    /// system has it, else sh.
+    pub(crate) fn as_login_args(&self) -> Vec<&str> {
+        match self {
+            Cmd::Shell(prog) => vec!["/bin/sh", "-c", LOGIN_SHELL, "sh", prog],
+            Cmd::Args(_) => self.as_args(),
+        }
+    }
+
     pub(crate) fn description(&self) -> Cow<'_, str> {
         match &self {
             Cmd::Shell(prog) => prog.into(),
@@ -31,8 +40,12 @@ impl Cmd {
     }
 }
 
-impl From<std::process::Command> for Cmd {
-    fn from(cmd: std::process::Command) -> Self {
+/// Runs `$1` in a login shell, so it sees what the user's profile sets up.
+const LOGIN_SHELL: &str =
+    r#"if command -v bash >/dev/null; then exec bash -lc "$1"; else exec sh -lc "$1"; fi"#;
+
+impl From<&std::process::Command> for Cmd {
+    fn from(cmd: &std::process::Command) -> Self {
         let mut args = vec1![cmd.get_program().to_string_lossy().to_string()];
         args.extend(cmd.get_args().map(|a| a.to_string_lossy().to_string()));
 
@@ -6,13 +6,24 @@ use indexmap::IndexMap;
 use crate::run;
 use crate::run::cmd::Cmd;
 
-pub(crate) struct DockerExec<'a> {
-    pub(crate) name: &'a str,
+/// Where and how to run commands in a container: everything about a [`DockerExec`] but the
+/// command itself.
+#[derive(Clone, Copy)]
+pub(crate) struct ExecContext<'a> {
     pub(crate) container: &'a str,
-    pub(crate) cmd: &'a Cmd,
     pub(crate) user: Option<&'a str>,
     pub(crate) workdir: Option<&'a Path>,
+    /// Run a shell command in a login shell.
+    pub(crate) login_shell: bool,
     pub(crate) env: &'a IndexMap<String, Option<String>>,
+    /// Passed through our own environment so values never show up in arguments.
+    pub(crate) secrets: &'a IndexMap<String, String>,
+}
+
+pub(crate) struct DockerExec<'a> {
+    pub(crate) name: &'a str,
+    pub(crate) cmd: &'a Cmd,
+    pub(crate) context: ExecContext<'a>,
 }
 
 impl run::Runnable for DockerExec<'_> {
@@ -25,19 +36,23 @@ impl run::Runnable for DockerExec<'_> {
     }
 
     async fn run(self, _: run::Token) -> eyre::Result<()> {
+        let context = self.context;
         let workdir_str;
         let mut args: Vec<&str> = vec!["exec"];
-        if let Some(u) = self.user {
+        for key in context.secrets.keys() {
+            args.extend(["-e", key]);
+        }
+        if let Some(u) = context.user {
             args.extend(["-u", u]);
         }
-        if let Some(w) = self.workdir {
+        if let Some(w) = context.workdir {
             workdir_str = w.to_string_lossy();
             args.extend(["-w", &workdir_str]);
         }
         // Per spec, `null` in remoteEnv means "unset" the variable. We can't actually unset PID-1
         // inherited vars via `docker exec -e`, so we set to empty string — closer to spec intent
         // than the reference impl, which stringifies `null` to the literal text "null".
-        let env_args: Vec<String> = self
+        let env_args: Vec<String> = context
             .env
             .iter()
             .map(|(k, v)| format!("{k}={}", v.as_deref().unwrap_or("")))
@@ -45,10 +60,15 @@ impl run::Runnable for DockerExec<'_> {
         for e in &env_args {
             args.extend(["-e", e]);
         }
-        args.push(self.container);
-        args.extend(self.cmd.as_args());
+        args.push(context.container);
+        if context.login_shell {
+            args.extend(self.cmd.as_login_args());
+        } else {
+            args.extend(self.cmd.as_args());
+        }
 
-        let full_argv: Vec<&str> = std::iter::once("docker").chain(args).collect();
-        super::run_cmd(&full_argv, None).await
+        let mut cmd = tokio::process::Command::new("docker");
+        cmd.args(args).envs(context.secrets);
+        super::run_command(cmd).await
     }
 }
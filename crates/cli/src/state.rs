@@ -10,13 +10,19 @@ use crate::{
     config::{Config, Project, ProjectName},
     devcontainer::{DevcontainerConfig, dc_options::DcOptions},
     docker::DockerClient,
-    workspace::Workspace,
+    helpers::resolve_workspace_name,
+    paths,
+    workspace::{Workspace, name_template},
     worktree,
 };
 
 pub(crate) struct State<'a> {
+    pub(crate) config: &'a Config,
     pub(crate) project_name: ProjectName,
     pub(crate) project: &'a Project,
+    /// Whether the project was picked explicitly, by `--project` or `DC_PROJECT`, rather than
+    /// inferred.
+    pub(crate) project_given: bool,
     pub(crate) devcontainer: Option<DevcontainerState>,
     working_dir: PathBuf,
 }
@@ -28,12 +34,12 @@ pub(crate) struct DevcontainerState {
 }
 
 impl DevcontainerState {
-    async fn new(project: &Project) -> eyre::Result<Option<Self>> {
-        let path = DevcontainerConfig::find_config(&project.path);
+    async fn new(project: &Project, namespace: &str) -> eyre::Result<Option<Self>> {
+        let path = project.find_devcontainer(&project.path)?;
         let Some(config) = DevcontainerConfig::load(path.as_deref(), project)? else {
             return Ok(None);
         };
-        let docker = DockerClient::new().await?;
+        let docker = DockerClient::new(namespace);
 
         Ok(Some(Self {
             path,
@@ -56,15 +62,19 @@ impl<'a> State<'a> {
         specified_project: Option<String>,
         config: &'a Config,
     ) -> eyre::Result<Self> {
+        let project_given = specified_project.is_some() || env::var_os("DC_PROJECT").is_some();
         let (project_name, project) = config.project(specified_project)?;
+        project.ensure_cloned().await?;
 
-        let devcontainer = DevcontainerState::new(project).await?;
+        let devcontainer = DevcontainerState::new(project, config.managed_label_value()).await?;
 
         let working_dir = Self::resolve_working_dir(&project_name, project, devcontainer.as_ref())?;
 
         Ok(Self {
+            config,
             project_name,
             project,
+            project_given,
             devcontainer,
             working_dir,
         })
@@ -102,10 +112,7 @@ impl<'a> State<'a> {
             })
         }) {
             Some(dir) => dir,
-            None => directories::ProjectDirs::from("", "", "devconcurrent")
-                .ok_or_eyre("could not determine data directory")?
-                .data_dir()
-                .join(project_name),
+            None => paths::data_dir()?.join(project_name),
         };
 
         Ok(if dir.is_relative() {
@@ -130,7 +137,32 @@ impl<'a> State<'a> {
         &self,
         name: Option<String>,
     ) -> eyre::Result<Workspace<'_>> {
-        self.try_resolve_workspace(name).await?.ok_or_else(|| {
+        self.resolve(name, true).await
+    }
+
+    /// Find the workspace. A given name (other than ".") always resolves, to
+    /// the existing workspace it's a unique prefix or fuzzy match of if there
+    /// isn't one by that exact name; otherwise we derive it from the current
+    /// working directory, returning `None` when the cwd isn't inside a
+    /// worktree.
+    pub(crate) async fn try_resolve_workspace(
+        &self,
+        name: Option<String>,
+    ) -> eyre::Result<Option<Workspace<'_>>> {
+        self.try_resolve(name, true).await
+    }
+
+    /// Like [`Self::resolve_workspace`], taking only an exact name, for commands that would do
+    /// damage on a wrong guess.
+    pub(crate) async fn resolve_exact_workspace(
+        &self,
+        name: Option<String>,
+    ) -> eyre::Result<Workspace<'_>> {
+        self.resolve(name, false).await
+    }
+
+    async fn resolve(&self, name: Option<String>, inexact: bool) -> eyre::Result<Workspace<'_>> {
+        self.try_resolve(name, inexact).await?.ok_or_else(|| {
             eyre::eyre!(
                 "no workspace specified and not inside a worktree of project '{}'",
                 self.project_name
@@ -138,18 +170,40 @@ impl<'a> State<'a> {
         })
     }
 
-    /// Find the workspace. A given name (other than ".") always resolves;
-    /// otherwise we derive it from the current working directory, returning
-    /// `None` when the cwd isn't inside a worktree.
-    pub(crate) async fn try_resolve_workspace(
+    /// The workspace named exactly `name`, whether or not it exists; no prefix or fuzzy matching.
+    pub(crate) async fn exact_workspace(&self, name: String) -> eyre::Result<Workspace<'_>> {
+        self.try_resolve(Some(name), false)
+            .await?
+            .ok_or_eyre("workspace not found")
+    }
+
+    async fn try_resolve(
         &self,
         name: Option<String>,
+        inexact: bool,
     ) -> eyre::Result<Option<Workspace<'_>>> {
         let worktrees = worktree::list(&self.project.path).await?;
 
-        if let Some(workspace_name) = name
+        if let Some(mut workspace_name) = name
             && workspace_name != "."
         {
+            if is_path_arg(&workspace_name) {
+                return self
+                    .workspace_at_path(Path::new(&workspace_name), worktrees)
+                    .map(Some);
+            }
+            if inexact {
+                let names: Vec<String> = worktrees
+                    .iter()
+                    .filter_map(|wt| Some(wt.file_name()?.to_string_lossy().into_owned()))
+                    .collect();
+                let names: Vec<&str> = names.iter().map(String::as_str).collect();
+                if let Some(found) =
+                    resolve_workspace_name(&workspace_name, &names).map_err(|e| eyre::eyre!(e))?
+                {
+                    workspace_name = found.to_owned();
+                }
+            }
             let path = worktrees
                 .into_iter()
                 .find(|wt| wt.file_name() == Some(workspace_name.as_ref()))
@@ -189,6 +243,72 @@ impl<'a> State<'a> {
         }))
     }
 
+    /// Like [`Self::resolve_workspace`], for a workspace that `up` may create: a name that
+    /// doesn't match an existing workspace goes through the project's `nameTemplate`.
+    pub(crate) async fn resolve_new_workspace(
+        &self,
+        name: Option<String>,
+    ) -> eyre::Result<Workspace<'_>> {
+        // Exact names only: `up api` shouldn't bring up `api-v2`.
+        let workspace = self.resolve_exact_workspace(name).await?;
+        let Some(template) = &self.project.name_template else {
+            return Ok(workspace);
+        };
+        if workspace.is_root || workspace.path.exists() {
+            return Ok(workspace);
+        }
+
+        let date = jiff::Zoned::now().strftime("%Y-%m-%d").to_string();
+        let user = env::var("USER").unwrap_or_default();
+        let tokens = name_template::Tokens {
+            name: &workspace.name,
+            project: &self.project_name,
+            date: &date,
+            user: &user,
+        };
+        let name = name_template::expand(template, &tokens)
+            .map_err(|e| eyre::eyre!("invalid nameTemplate: {e}"))?;
+        // A given name always resolves.
+        self.try_resolve(Some(name), false)
+            .await?
+            .ok_or_eyre("workspace not found")
+    }
+
+    /// The workspace for an existing worktree given by path, which may live outside the project
+    /// working directory.
+    fn workspace_at_path(
+        &self,
+        path: &Path,
+        worktrees: Vec<PathBuf>,
+    ) -> eyre::Result<Workspace<'_>> {
+        let canonical = path
+            .canonicalize()
+            .map_err(|e| eyre::eyre!("invalid workspace path {}: {e}", path.display()))?;
+        let path = worktrees
+            .into_iter()
+            .find(|wt| wt.canonicalize().is_ok_and(|wt| wt == canonical))
+            .ok_or_else(|| {
+                eyre::eyre!(
+                    "{} is not a worktree of project '{}'",
+                    path.display(),
+                    self.project_name
+                )
+            })?;
+        let name = path
+            .file_name()
+            .ok_or_eyre("worktree path has no basename")?
+            .to_string_lossy()
+            .to_string();
+        let is_root = self.is_root(&name);
+
+        Ok(Workspace {
+            state: self,
+            name,
+            path,
+            is_root,
+        })
+    }
+
     pub(crate) fn try_devcontainer(&self) -> eyre::Result<&DevcontainerState> {
         self.devcontainer.as_ref().ok_or_else(|| eyre::eyre!("no devcontainer.json found for this project; devcontainer functionality is disabled"))
     }
@@ -203,7 +323,7 @@ impl<'a> State<'a> {
         workspace_path: &Path,
     ) -> eyre::Result<DevcontainerState> {
         let root = self.try_devcontainer()?;
-        let path = DevcontainerConfig::find_config(workspace_path);
+        let path = self.project.find_devcontainer(workspace_path)?;
         let config = DevcontainerConfig::load(path.as_deref(), self.project)?.ok_or_else(|| {
             eyre::eyre!(
                 "no devcontainer.json found in workspace {}",
@@ -218,3 +338,9 @@ impl<'a> State<'a> {
         })
     }
 }
+
+/// Whether a workspace argument is a path to a worktree rather than a name. Names can't contain
+/// `/`, so anything that does is a path.
+pub(crate) fn is_path_arg(arg: &str) -> bool {
+    arg.contains('/')
+}
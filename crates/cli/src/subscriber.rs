@@ -15,13 +15,43 @@ use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
 use tracing_subscriber::registry::LookupSpan;
 use tracing_subscriber::util::SubscriberInitExt;
 
-use crate::ansi::{BLUE, GRAY, GREEN, MAGENTA, RED, RESET, YELLOW};
+use crate::ansi::{BLUE, GRAY, GREEN, MAGENTA, RED, RESET, YELLOW, strip_ansi};
+use crate::redact::{redact, register_from_env};
 
 fn ts(time: &Zoned) -> String {
     time.strftime("%F %T").to_string()
 }
 
-pub(crate) fn init_subscriber() {
+/// How progress is reported on stderr.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub(crate) enum Progress {
+    /// Spinners and log lines.
+    #[default]
+    Human,
+    /// One JSON object per line: spans starting and finishing, logs, and command output.
+    Json,
+}
+
+static JSON_PROGRESS: AtomicBool = AtomicBool::new(false);
+
+/// Whether progress is reported as JSON, so output that bypasses tracing should be too.
+pub(crate) fn json_progress() -> bool {
+    JSON_PROGRESS.load(Ordering::Relaxed)
+}
+
+/// `quiet` drops the span start/finish lines and info and debug logs, leaving warnings, errors, and
+/// command output.
+pub(crate) fn init_subscriber(progress: Progress, quiet: bool) {
+    register_from_env();
+
+    if progress == Progress::Json {
+        JSON_PROGRESS.store(true, Ordering::Relaxed);
+        tracing_subscriber::registry()
+            .with(JsonLayer.with_filter(filter_fn(ours_or_important)))
+            .init();
+        return;
+    }
+
     let indicatif_layer = IndicatifLayer::new().with_progress_style(
         ProgressStyle::with_template("{span_child_prefix}{spinner} {elapsed} {msg}")
             .expect("invalid progress style template"),
@@ -29,10 +59,11 @@ pub(crate) fn init_subscriber() {
     let stderr_writer = indicatif_layer.get_stderr_writer();
     let indicatif_layer = indicatif_layer.with_filter(IndicatifFilter::new(false));
 
-    let dc_layer = DcLayer { stderr_writer }.with_filter(filter_fn(|meta| {
-        // Filter out verbose (TRACE) output from dependencies.
-        *meta.level() < tracing::Level::DEBUG || meta.target().starts_with("devconcurrent")
-    }));
+    let dc_layer = DcLayer {
+        stderr_writer,
+        quiet,
+    }
+    .with_filter(filter_fn(ours_or_important));
 
     tracing_subscriber::registry()
         .with(dc_layer)
@@ -40,6 +71,11 @@ pub(crate) fn init_subscriber() {
         .init();
 }
 
+/// Filter out verbose (debug and trace) output from dependencies.
+fn ours_or_important(meta: &tracing::Metadata<'_>) -> bool {
+    *meta.level() < tracing::Level::DEBUG || meta.target().starts_with("devconcurrent")
+}
+
 struct HasIndicatif;
 struct IndicatifName(String);
 
@@ -54,6 +90,7 @@ struct SpanTiming {
 
 struct DcLayer {
     stderr_writer: IndicatifWriter<Stderr>,
+    quiet: bool,
 }
 
 impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for DcLayer {
@@ -90,7 +127,7 @@ impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for DcLayer {
             return;
         };
 
-        if timing.entered.swap(true, Ordering::Relaxed) {
+        if timing.entered.swap(true, Ordering::Relaxed) || self.quiet {
             return;
         }
 
@@ -103,7 +140,7 @@ impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for DcLayer {
             line.push_str(&format!(" {message}:"));
         }
         if let Some(ref description) = timing.description {
-            line.push_str(&format!(" {description}"));
+            line.push_str(&format!(" {}", redact(description)));
         }
         let mut stderr = self.stderr_writer.clone();
         let _ = writeln!(stderr, "{line}");
@@ -111,6 +148,9 @@ impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for DcLayer {
     }
 
     fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        if self.quiet {
+            return;
+        }
         let Some(span) = ctx.span(&id) else { return };
         let extensions = span.extensions();
         let Some(timing) = extensions.get::<SpanTiming>() else {
@@ -144,6 +184,7 @@ impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for DcLayer {
         let mut visitor = Visitor::default();
         event.record(&mut visitor);
         let msg = visitor.message.unwrap_or_default();
+        let msg = redact(&msg);
 
         // Find indicatif name from ancestor spans
         let name = ctx.event_span(event).and_then(|span| {
@@ -167,6 +208,10 @@ impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for DcLayer {
             return;
         }
 
+        if self.quiet && level > tracing::Level::WARN {
+            return;
+        }
+
         let ts = ts(&Zoned::now());
         let level_color = match level {
             tracing::Level::ERROR => RED,
@@ -188,6 +233,108 @@ impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for DcLayer {
     }
 }
 
+// -- JSON progress -----------------------------------------------------------
+
+/// Print one JSON progress event to stderr.
+pub(crate) fn emit_json(mut event: serde_json::Value) {
+    event["time"] = jiff::Timestamp::now().to_string().into();
+    let mut stderr = std::io::stderr().lock();
+    let _ = writeln!(stderr, "{event}");
+}
+
+fn clean(s: &str) -> String {
+    strip_ansi(&redact(s))
+}
+
+struct JsonLayer;
+
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for JsonLayer {
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else { return };
+
+        let mut visitor = Visitor::default();
+        attrs.record(&mut visitor);
+
+        if span.name() == "parallel"
+            && let Some(ref name) = visitor.name
+        {
+            span.extensions_mut().insert(IndicatifName(name.clone()));
+        }
+
+        span.extensions_mut().insert(SpanTiming {
+            name: visitor.name,
+            description: visitor.description,
+            message: visitor.message,
+            finish_message: visitor.finish_message,
+            start: Zoned::now(),
+            entered: AtomicBool::new(false),
+        });
+    }
+
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else { return };
+        let extensions = span.extensions();
+        let Some(timing) = extensions.get::<SpanTiming>() else {
+            return;
+        };
+        if timing.entered.swap(true, Ordering::Relaxed) {
+            return;
+        }
+
+        emit_json(serde_json::json!({
+            "event": "start",
+            "span": span.name(),
+            "name": timing.name.as_deref().map(clean),
+            "message": timing.message.as_deref().map(clean),
+            "description": timing.description.as_deref().map(clean),
+        }));
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else { return };
+        let extensions = span.extensions();
+        let Some(timing) = extensions.get::<SpanTiming>() else {
+            return;
+        };
+        if !timing.entered.load(Ordering::Relaxed) {
+            return;
+        }
+
+        let ms = timing.start.duration_until(&Zoned::now()).as_millis();
+        emit_json(serde_json::json!({
+            "event": "finish",
+            "span": span.name(),
+            "name": timing.name.as_deref().map(clean),
+            "message": timing.finish_message.as_deref().map(clean),
+            "duration_ms": ms,
+        }));
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let meta = event.metadata();
+        let mut visitor = Visitor::default();
+        event.record(&mut visitor);
+        let msg = clean(&visitor.message.unwrap_or_default());
+        let name = ctx.event_span(event).and_then(|span| {
+            span.scope()
+                .find_map(|s| s.extensions().get::<IndicatifName>().map(|n| n.0.clone()))
+        });
+
+        // TRACE events are forwarded command output.
+        let event = if *meta.level() == tracing::Level::TRACE {
+            serde_json::json!({ "event": "output", "name": name, "line": msg })
+        } else {
+            serde_json::json!({
+                "event": "log",
+                "level": meta.level().as_str().to_lowercase(),
+                "name": name,
+                "message": msg,
+            })
+        };
+        emit_json(event);
+    }
+}
+
 // -- Visitor -----------------------------------------------------------------
 
 #[derive(Default)]
@@ -77,6 +77,33 @@ pub(crate) struct BuiltCell {
     ready: BoxFuture<'static, ()>,
 }
 
+/// Fold several sources into one, e.g. for a total. Pending until every source has a value.
+pub(crate) fn combine<V, T>(
+    sources: Vec<ValueSource<V>>,
+    fold: impl Fn(Vec<Datum<V>>) -> Datum<T> + Send + Sync + 'static,
+) -> ValueSource<T>
+where
+    V: 'static,
+{
+    let gets: Vec<_> = sources.iter().map(|s| s.get.clone()).collect();
+    let readies: Vec<_> = sources.into_iter().map(|s| s.ready).collect();
+    let get = Arc::new(move || {
+        let values: Vec<Datum<V>> = gets.iter().map(|get| get()).collect();
+        if values.iter().any(|v| matches!(v, Datum::Pending)) {
+            Datum::Pending
+        } else {
+            fold(values)
+        }
+    });
+    let ready = Arc::new(move || {
+        let all = futures::future::join_all(readies.iter().map(|ready| ready()));
+        Box::pin(async move {
+            all.await;
+        }) as BoxFuture<'static, ()>
+    });
+    ValueSource::new(get, ready)
+}
+
 /// An immediately-available cell.
 pub(crate) fn text(s: impl Into<String>) -> BuiltCell {
     struct Static(String);
@@ -117,11 +144,19 @@ where
 
 /// A column: a header, alignment, and a projection from a row `T` to a cell.
 pub(crate) struct ColumnDef<T> {
-    header: &'static str,
-    align: Align,
+    header: Header,
     make: Box<dyn Fn(&T) -> BuiltCell>,
 }
 
+/// A column's presentation, kept once the rows are built.
+#[derive(Clone, Copy)]
+struct Header {
+    name: &'static str,
+    align: Align,
+    /// May be hidden when the table doesn't fit the terminal.
+    optional: bool,
+}
+
 impl<T> ColumnDef<T> {
     pub(crate) fn new(
         header: &'static str,
@@ -129,11 +164,21 @@ impl<T> ColumnDef<T> {
         make: impl Fn(&T) -> BuiltCell + 'static,
     ) -> Self {
         ColumnDef {
-            header,
-            align,
+            header: Header {
+                name: header,
+                align,
+                optional: false,
+            },
             make: Box::new(make),
         }
     }
+
+    /// Let this column be hidden on a terminal too narrow for the table.
+    /// Optional columns are hidden right to left.
+    pub(crate) fn optional(mut self) -> Self {
+        self.header.optional = true;
+        self
+    }
 }
 
 /// A set of columns; `collect` from an iterator of [`Column`]s, then `build`.
@@ -152,8 +197,7 @@ impl<T> FromIterator<ColumnDef<T>> for TableBuilder<T> {
 impl<T> TableBuilder<T> {
     /// Apply the columns to every row, erasing `T`.
     pub(crate) fn build(self, rows: &[T], live: bool) -> Table {
-        let headers: Vec<(&'static str, Align)> =
-            self.columns.iter().map(|c| (c.header, c.align)).collect();
+        let headers: Vec<Header> = self.columns.iter().map(|c| c.header).collect();
 
         let mut grid: Vec<Vec<Box<dyn CellSource>>> = Vec::with_capacity(rows.len());
         let mut ready: Vec<BoxFuture<'static, ()>> = Vec::new();
@@ -172,16 +216,38 @@ impl<T> TableBuilder<T> {
             grid,
             ready,
             live,
+            wide: false,
         }
     }
 }
 
 /// A built, presentation-only table.
 pub(crate) struct Table {
-    headers: Vec<(&'static str, Align)>,
+    headers: Vec<Header>,
     grid: Vec<Vec<Box<dyn CellSource>>>,
     ready: Vec<BoxFuture<'static, ()>>,
     live: bool,
+    /// Show every column, and let lines wrap, however narrow the terminal.
+    wide: bool,
+}
+
+impl Table {
+    pub(crate) fn wide(mut self, wide: bool) -> Self {
+        self.wide = wide;
+        self
+    }
+
+    /// Add a last row, e.g. of totals; one cell per column.
+    pub(crate) fn footer(mut self, cells: impl IntoIterator<Item = BuiltCell>) -> Self {
+        let mut row = Vec::with_capacity(self.headers.len());
+        for cell in cells {
+            row.push(cell.source);
+            self.ready.push(cell.ready);
+        }
+        debug_assert_eq!(row.len(), self.headers.len());
+        self.grid.push(row);
+        self
+    }
 }
 
 /// Dimmed placeholder for an unresolved cell.
@@ -12,7 +12,7 @@ use crossterm::{cursor, queue, terminal};
 use tabular::{Row, Table as TabularTable};
 
 use super::{CellState, Table};
-use crate::ansi::{GRAY, RESET};
+use crate::ansi::{GRAY, RESET, color_enabled, strip_ansi};
 
 /// How long the non-live / piped paths wait before showing `-` for whatever is
 /// still pending.
@@ -33,31 +33,52 @@ impl Table {
 
     /// Render the table to a string and its line count. Pending cells show the
     /// spinner frame, or `-` on the final frame.
+    ///
+    /// Unless the table is wide, optional columns are hidden until it fits in
+    /// `width`, and whatever still doesn't fit is cut off.
     fn render_block(&self, frame: usize, final_frame: bool, width: u16) -> (String, u16) {
-        let spec = self
-            .headers
+        let color = color_enabled();
+        let spinner = format!("{GRAY}{}{RESET}", SPINNER[frame % SPINNER.len()]);
+        let grid: Vec<Vec<String>> = self
+            .grid
+            .iter()
+            .map(|cells| {
+                cells
+                    .iter()
+                    .map(|cell| {
+                        let s = match cell.get() {
+                            CellState::Ready(s) => s,
+                            CellState::Pending if final_frame => super::dash(),
+                            CellState::Pending => spinner.clone(),
+                        };
+                        // Cells are colored by several libraries; strip them all here.
+                        if color { s } else { strip_ansi(&s) }
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let width = if self.wide { u16::MAX } else { width };
+        let shown = self.shown_columns(&grid, width.into());
+        let spec = shown
             .iter()
-            .map(|(_, align)| align.spec())
+            .map(|&i| self.headers[i].align.spec())
             .collect::<Vec<_>>()
             .join("  ");
         let mut table = TabularTable::new(&spec);
 
         let mut header = Row::new();
-        for (h, _) in &self.headers {
-            header.add_cell(*h);
+        for &i in &shown {
+            header.add_cell(self.headers[i].name);
         }
         table.add_row(header);
 
-        let spinner = format!("{GRAY}{}{RESET}", SPINNER[frame % SPINNER.len()]);
-        for cells in &self.grid {
+        for cells in grid {
             let mut row = Row::new();
-            for cell in cells {
-                let s = match cell.get() {
-                    CellState::Ready(s) => s,
-                    CellState::Pending if final_frame => super::dash(),
-                    CellState::Pending => spinner.clone(),
-                };
-                row.add_ansi_cell(s);
+            for (i, s) in cells.into_iter().enumerate() {
+                if shown.contains(&i) {
+                    row.add_ansi_cell(s);
+                }
             }
             table.add_row(row);
         }
@@ -73,6 +94,37 @@ impl Table {
         (out, lines)
     }
 
+    /// The indices of the columns to show: all of them, less optional ones
+    /// (rightmost first) while the table is wider than `width`.
+    fn shown_columns(&self, grid: &[Vec<String>], width: usize) -> Vec<usize> {
+        let widths: Vec<usize> = self
+            .headers
+            .iter()
+            .enumerate()
+            .map(|(i, h)| {
+                grid.iter()
+                    .map(|cells| visible_width(&cells[i]))
+                    .chain([h.name.chars().count()])
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+        let total = |shown: &[usize]| {
+            shown.iter().map(|&i| widths[i]).sum::<usize>() + 2 * shown.len().saturating_sub(1)
+        };
+
+        let mut shown: Vec<usize> = (0..self.headers.len()).collect();
+        for i in (0..self.headers.len()).rev() {
+            if total(&shown) <= width {
+                break;
+            }
+            if self.headers[i].optional {
+                shown.retain(|&j| j != i);
+            }
+        }
+        shown
+    }
+
     /// Render a final, static frame to a string — for callers that already have
     /// all their data and just want the aligned output (no spinner, no
     /// truncation, no cursor control).
@@ -135,6 +187,11 @@ impl Table {
     }
 }
 
+/// The number of visible columns in `s`, ignoring ANSI escapes.
+fn visible_width(s: &str) -> usize {
+    strip_ansi(s).chars().count()
+}
+
 /// Truncate to `max` visible columns, copying ANSI escapes verbatim and
 /// resetting if cut. Keeps each row one physical line so `MoveUp` stays correct.
 fn truncate_visible(line: &str, max: u16) -> String {
@@ -169,3 +226,46 @@ fn truncate_visible(line: &str, max: u16) -> String {
     }
     out
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::table::{Align, ColumnDef, TableBuilder, text};
+
+    fn table() -> Table {
+        [
+            ColumnDef::new("NAME", Align::Left, |r: &&str| text(*r)),
+            ColumnDef::new("MEM", Align::Right, |_: &&str| text("1.2GiB")).optional(),
+            ColumnDef::new("GIT", Align::Left, |_: &&str| text("3 changed")).optional(),
+        ]
+        .into_iter()
+        .collect::<TableBuilder<&str>>()
+        .build(&["main", "feature"], false)
+    }
+
+    #[test]
+    fn hides_optional_columns_right_to_left() {
+        let t = table();
+        let header = |width| {
+            t.render_block(0, true, width)
+                .0
+                .lines()
+                .next()
+                .unwrap()
+                .to_owned()
+        };
+        assert_eq!(header(u16::MAX).split_whitespace().count(), 3);
+        assert_eq!(
+            header(17).split_whitespace().collect::<Vec<_>>(),
+            ["NAME", "MEM"]
+        );
+        assert_eq!(header(10).split_whitespace().collect::<Vec<_>>(), ["NAME"]);
+    }
+
+    #[test]
+    fn wide_shows_every_column() {
+        let t = table().wide(true);
+        let (block, _) = t.render_block(0, true, 10);
+        assert!(block.lines().next().unwrap().ends_with("GIT"));
+    }
+}
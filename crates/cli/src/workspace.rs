@@ -1,13 +1,15 @@
 use std::path::PathBuf;
 
-use docker::{ContainerStatus, FORWARD_LABEL, PROJECT_LABEL, WORKSPACE_LABEL};
+use docker::{ContainerStatus, FORWARD_LABEL, MANAGED_LABEL, PROJECT_LABEL, WORKSPACE_LABEL};
 use eyre::eyre;
 
-use crate::docker::ContainerInfo;
+use crate::docker::{ContainerInfo, DockerClient};
 use crate::state::{DevcontainerState, State};
 use crate::worktree;
 
 pub(crate) mod git_status;
+pub(crate) mod metadata;
+pub(crate) mod name_template;
 
 pub(crate) struct Workspace<'a> {
     pub(crate) state: &'a State<'a>,
@@ -37,8 +39,8 @@ impl<'a> Workspace<'a> {
         })
     }
 
-    pub(crate) async fn is_dirty(&self) -> eyre::Result<bool> {
-        Ok(git_status::GitStatus::fetch(&self.path).await?.is_dirty())
+    pub(crate) async fn git_status(&self) -> eyre::Result<git_status::GitStatus> {
+        git_status::GitStatus::fetch(&self.path, &self.state.project.dirty_check).await
     }
 
     /// Match the devcontainer CLI convention: `{basename}_devcontainer`, lowercased,
@@ -64,8 +66,13 @@ impl<'a> Workspace<'a> {
         (FORWARD_LABEL, "true")
     }
 
-    pub(crate) fn docker_fwd_labels(&self) -> [(&str, &str); 3] {
+    pub(crate) fn managed_label(&self) -> (&str, &str) {
+        (MANAGED_LABEL, self.state.config.managed_label_value())
+    }
+
+    pub(crate) fn docker_fwd_labels(&self) -> [(&str, &str); 4] {
         [
+            self.managed_label(),
             self.project_label(),
             self.workspace_label(),
             self.fwd_label(),
@@ -92,6 +99,13 @@ impl WorkspaceDevcontainer {
         self.containers.iter().map(|c| c.state).max()
     }
 
+    /// Whether anyone is exec'd into any of the workspace's containers.
+    pub(crate) async fn has_execs(&self, docker: &DockerClient) -> bool {
+        let counts =
+            futures::future::join_all(self.containers.iter().map(|c| docker.execs(&c.id))).await;
+        counts.into_iter().any(|n| n.is_ok_and(|n| n > 0))
+    }
+
     pub(crate) fn service_container_id(&self) -> eyre::Result<&str> {
         // FIXME: We need to find the correct service container.
         Ok(&self
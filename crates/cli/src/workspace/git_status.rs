@@ -3,6 +3,8 @@ use std::path::Path;
 
 use owo_colors::OwoColorize;
 
+use crate::config::DirtyCheck;
+
 #[derive(Debug, Default)]
 pub(crate) struct GitStatus {
     pub(crate) ahead: usize,
@@ -13,15 +15,18 @@ pub(crate) struct GitStatus {
     pub(crate) untracked: usize,
     pub(crate) conflicted: usize,
     pub(crate) renamed: usize,
+    /// Commits on HEAD not reachable from any remote-tracking branch.
+    pub(crate) unpushed: usize,
 }
 
 impl GitStatus {
-    pub(crate) async fn fetch(path: &Path) -> eyre::Result<Self> {
+    pub(crate) async fn fetch(path: &Path, check: &DirtyCheck) -> eyre::Result<Self> {
         if !path.exists() {
             return Ok(Self::default());
         }
         let path = path.to_owned();
-        tokio::task::spawn_blocking(move || fetch_sync(&path)).await?
+        let check = check.clone();
+        tokio::task::spawn_blocking(move || fetch_sync(&path, &check)).await?
     }
 
     pub(crate) fn is_dirty(&self) -> bool {
@@ -30,18 +35,20 @@ impl GitStatus {
     }
 }
 
-fn fetch_sync(path: &Path) -> eyre::Result<GitStatus> {
+fn fetch_sync(path: &Path, check: &DirtyCheck) -> eyre::Result<GitStatus> {
     let repo = gix::open(path)?;
     let mut gs = GitStatus::default();
 
     let (ahead, behind) = ahead_behind(&repo).unwrap_or((0, 0));
     gs.ahead = ahead;
     gs.behind = behind;
+    gs.unpushed = unpushed(&repo).unwrap_or(0);
 
     // Use `git status` instead of gix's status API — the latter doesn't refresh
     // the index stat cache and reports false modifications in worktrees.
     let output = std::process::Command::new("git")
         .args(["status", "--porcelain=v1", "-z"])
+        .args(status_args(check))
         .current_dir(path)
         .output()?;
 
@@ -83,6 +90,43 @@ fn fetch_sync(path: &Path) -> eyre::Result<GitStatus> {
     Ok(gs)
 }
 
+/// Extra `git status` arguments restricting what we consider.
+fn status_args(check: &DirtyCheck) -> Vec<&str> {
+    match check {
+        DirtyCheck::All => vec![],
+        DirtyCheck::Tracked => vec!["--untracked-files=no"],
+        DirtyCheck::Pathspec(specs) => std::iter::once("--")
+            .chain(specs.iter().map(String::as_str))
+            .collect(),
+    }
+}
+
+/// Count commits on HEAD that no remote has seen. Unlike `ahead`, this doesn't
+/// need a tracking branch, so it catches local-only branches too.
+///
+/// Repositories without any remote-tracking branches have nowhere to push, so
+/// nothing counts as unpushed.
+fn unpushed(repo: &gix::Repository) -> eyre::Result<usize> {
+    let head_id = repo.head_id()?.detach();
+    let remote_ids: Vec<gix::ObjectId> = repo
+        .references()?
+        .remote_branches()?
+        .filter_map(Result::ok)
+        .filter_map(|mut r| r.peel_to_id().ok().map(gix::Id::detach))
+        .collect();
+    if remote_ids.is_empty() {
+        return Ok(0);
+    }
+
+    let count = repo
+        .rev_walk([head_id])
+        .with_hidden(remote_ids)
+        .all()?
+        .filter_map(Result::ok)
+        .count();
+    Ok(count)
+}
+
 fn ahead_behind(repo: &gix::Repository) -> eyre::Result<(usize, usize)> {
     let head = repo.head()?;
     let head_id = head
@@ -0,0 +1,76 @@
+//! `.dc/workspace.json`: a record of where a workspace came from, written when `up` creates its
+//! worktree. Readable from inside the container, and gives us its identity without re-deriving
+//! it from paths.
+
+use std::path::{Path, PathBuf};
+
+use eyre::WrapErr;
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+
+use crate::workspace::Workspace;
+
+const DIR: &str = ".dc";
+const FILE: &str = "workspace.json";
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Metadata {
+    pub(crate) project: String,
+    pub(crate) workspace: String,
+    /// RFC 3339 timestamp.
+    pub(crate) created: String,
+    /// The commit the worktree was created at.
+    pub(crate) base_ref: Option<String>,
+    pub(crate) compose_project: String,
+}
+
+fn path(worktree: &Path) -> PathBuf {
+    worktree.join(DIR).join(FILE)
+}
+
+impl Metadata {
+    /// Record `workspace`'s metadata in its worktree, unless it already has some.
+    pub(crate) async fn write(workspace: &Workspace<'_>) -> eyre::Result<()> {
+        let path = path(&workspace.path);
+        if path.exists() {
+            return Ok(());
+        }
+
+        let metadata = Metadata {
+            project: workspace.state.project_name.to_string(),
+            workspace: workspace.name.clone(),
+            created: jiff::Timestamp::now().to_string(),
+            base_ref: head(&workspace.path).await,
+            compose_project: workspace.compose_project_name(),
+        };
+
+        let dir = workspace.path.join(DIR);
+        std::fs::create_dir_all(&dir)
+            .wrap_err_with(|| format!("failed to create {}", dir.display()))?;
+        // Keep it out of `git status`, so it doesn't make the workspace dirty.
+        std::fs::write(dir.join(".gitignore"), "*\n")?;
+        std::fs::write(&path, serde_json::to_string_pretty(&metadata)?)
+            .wrap_err_with(|| format!("failed to write {}", path.display()))
+    }
+
+    /// The metadata recorded in `worktree`, if any.
+    pub(crate) fn read(worktree: &Path) -> Option<Self> {
+        let contents = std::fs::read_to_string(path(worktree)).ok()?;
+        serde_json::from_str(&contents)
+            .inspect_err(|e| tracing::debug!("ignoring invalid {DIR}/{FILE}: {e}"))
+            .ok()
+    }
+}
+
+async fn head(worktree: &Path) -> Option<String> {
+    let out = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .current_dir(worktree)
+        .output()
+        .await
+        .ok()?;
+    out.status
+        .success()
+        .then(|| String::from_utf8_lossy(&out.stdout).trim().to_owned())
+}
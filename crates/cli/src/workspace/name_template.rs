@@ -0,0 +1,84 @@
+//! `nameTemplate`: derive new workspace names from a per-project template.
+
+use crate::helpers::validate_name;
+
+/// Values for the template tokens.
+pub(crate) struct Tokens<'a> {
+    pub(crate) name: &'a str,
+    pub(crate) project: &'a str,
+    pub(crate) date: &'a str,
+    pub(crate) user: &'a str,
+}
+
+impl<'a> Tokens<'a> {
+    fn get(&self, token: &str) -> Option<&'a str> {
+        Some(match token {
+            "name" => self.name,
+            "project" => self.project,
+            "date" => self.date,
+            "user" => self.user,
+            _ => return None,
+        })
+    }
+}
+
+/// Expand `template`, checking that the result is a valid workspace name.
+///
+/// `{name}` is required, so that different names can't expand to the same workspace.
+pub(crate) fn expand(template: &str, tokens: &Tokens) -> Result<String, String> {
+    let mut result = String::new();
+    let mut has_name = false;
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        result.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| format!("unclosed `{{` in {template:?}"))?;
+        let token = &rest[start + 1..start + end];
+        let value = tokens
+            .get(token)
+            .ok_or_else(|| format!("unknown token `{{{token}}}` in {template:?}"))?;
+        has_name |= token == "name";
+        result.push_str(value);
+        rest = &rest[start + end + 1..];
+    }
+    result.push_str(rest);
+
+    if !has_name {
+        return Err(format!("{template:?} must contain `{{name}}`"));
+    }
+    validate_name(&result)?;
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOKENS: Tokens = Tokens {
+        name: "fix-login",
+        project: "web",
+        date: "2026-10-17",
+        user: "paho",
+    };
+
+    #[test]
+    fn expands_tokens() {
+        assert_eq!(
+            expand("{user}_{name}-{date}", &TOKENS).unwrap(),
+            "paho_fix-login-2026-10-17"
+        );
+        assert_eq!(
+            expand("{project}-{name}", &TOKENS).unwrap(),
+            "web-fix-login"
+        );
+    }
+
+    #[test]
+    fn rejects_bad_templates() {
+        assert!(expand("{date}", &TOKENS).is_err());
+        assert!(expand("{name}-{branch}", &TOKENS).is_err());
+        assert!(expand("{name", &TOKENS).is_err());
+        assert!(expand("{name}/x", &TOKENS).is_err());
+    }
+}
@@ -5,11 +5,13 @@ use eyre::WrapErr;
 use tokio::process::Command;
 
 use crate::helpers::validate_name;
+use crate::preflight;
 use crate::run::run_cmd;
 use crate::workspace::Workspace;
 
 pub(crate) async fn create(workspace: &Workspace<'_>, detach: bool) -> eyre::Result<()> {
     validate_name(&workspace.name).map_err(|e| eyre::eyre!("invalid workspace name: {e}"))?;
+    preflight::check_git()?;
 
     let root_path = &workspace.state.project.path;
     let repo = gix::open(root_path)
@@ -26,11 +28,25 @@ pub(crate) async fn create(workspace: &Workspace<'_>, detach: bool) -> eyre::Res
             eyre::bail!("existing repository at {worktree_path_str}");
         }
     } else {
+        if !detach {
+            // With no branch given, git checks out a branch named after the
+            // worktree directory, which it refuses to do if another worktree
+            // already has it.
+            let branch = &workspace.name;
+            if let Some(other) = branch_checked_out_at(root_path, branch).await? {
+                eyre::bail!(
+                    "branch '{branch}' is already checked out at {}; use --detach or a different workspace name",
+                    other.display()
+                );
+            }
+        }
+
         let mut args = vec!["git", "worktree", "add", &worktree_path_str];
         if detach {
             args.push("--detach");
         }
         workspace.state.ensure_project_working_dir()?;
+        preflight::warn_if_volatile(workspace.state.project_working_dir());
         run_cmd(&args, Some(root_path)).await?;
     }
 
@@ -65,6 +81,7 @@ async fn lock(workspace: &Workspace<'_>) -> eyre::Result<()> {
 }
 
 async fn worktree_list(repo_path: &Path) -> eyre::Result<Output> {
+    preflight::check_git()?;
     Command::new("git")
         .args(["worktree", "list", "--porcelain"])
         .current_dir(repo_path)
@@ -75,6 +92,7 @@ async fn worktree_list(repo_path: &Path) -> eyre::Result<Output> {
 
 // We want a sync version for the completer
 fn worktree_list_sync(repo_path: &Path) -> eyre::Result<Output> {
+    preflight::check_git()?;
     std::process::Command::new("git")
         .args(["worktree", "list", "--porcelain"])
         .current_dir(repo_path)
@@ -93,6 +111,50 @@ fn process_list(out: Output) -> eyre::Result<Vec<PathBuf>> {
         .collect())
 }
 
+/// Find the worktree, if any, that has `branch` checked out.
+async fn branch_checked_out_at(repo_path: &Path, branch: &str) -> eyre::Result<Option<PathBuf>> {
+    let out = worktree_list(repo_path).await?;
+    eyre::ensure!(out.status.success(), "git worktree list failed");
+    let output =
+        String::from_utf8(out.stdout).wrap_err("git worktree list output is not valid UTF-8")?;
+    Ok(find_branch(&output, branch))
+}
+
+/// Porcelain output is a blank-line separated block per worktree, starting
+/// with its `worktree` line and, unless detached, including a `branch` line.
+fn find_branch(porcelain: &str, branch: &str) -> Option<PathBuf> {
+    let full_ref = format!("refs/heads/{branch}");
+    let mut current = None;
+    for line in porcelain.lines() {
+        if let Some(path) = line.strip_prefix("worktree ") {
+            current = Some(path);
+        } else if line.strip_prefix("branch ") == Some(full_ref.as_str()) {
+            return current.map(PathBuf::from);
+        }
+    }
+    None
+}
+
+/// Whether a listed worktree has lost its checkout, e.g. to an `rm -rf`. Git keeps listing such
+/// worktrees, and since ours are locked, `git worktree prune` leaves them be; see [`prune`].
+pub(crate) fn is_broken(path: &Path) -> bool {
+    !path.join(".git").exists()
+}
+
+/// Have git forget the given broken worktrees.
+pub(crate) async fn prune(repo_path: &Path, broken: &[PathBuf]) -> eyre::Result<()> {
+    for path in broken {
+        // Swallow errors; we don't care if it was not locked.
+        let _ = Command::new("git")
+            .args(["worktree", "unlock"])
+            .arg(path)
+            .current_dir(repo_path)
+            .output()
+            .await;
+    }
+    run_cmd(&["git", "worktree", "prune"], Some(repo_path)).await
+}
+
 pub(crate) async fn list(repo_path: &Path) -> eyre::Result<Vec<PathBuf>> {
     let out = worktree_list(repo_path).await?;
     process_list(out)
@@ -103,3 +165,68 @@ pub(crate) fn list_sync(repo_path: &Path) -> eyre::Result<Vec<PathBuf>> {
     let out = worktree_list_sync(repo_path)?;
     process_list(out)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PORCELAIN: &str = "\
+worktree /repo
+HEAD 1111111111111111111111111111111111111111
+branch refs/heads/main
+
+worktree /work/foo
+HEAD 2222222222222222222222222222222222222222
+branch refs/heads/foo
+
+worktree /work/bar
+HEAD 3333333333333333333333333333333333333333
+detached
+";
+
+    #[test]
+    fn find_branch_names_worktree() {
+        assert_eq!(
+            find_branch(PORCELAIN, "foo"),
+            Some(PathBuf::from("/work/foo"))
+        );
+        assert_eq!(find_branch(PORCELAIN, "main"), Some(PathBuf::from("/repo")));
+    }
+
+    #[tokio::test]
+    async fn prune_forgets_deleted_locked_worktrees() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path().join("repo");
+        let git = |args: &[&str]| {
+            let out = std::process::Command::new("git")
+                .args(["-c", "user.name=t", "-c", "user.email=t@t"])
+                .args(args)
+                .current_dir(dir.path())
+                .output()
+                .unwrap();
+            assert!(out.status.success(), "{out:?}");
+        };
+        git(&["init", "-q", "repo"]);
+        git(&["-C", "repo", "commit", "-q", "--allow-empty", "-m", "init"]);
+        git(&["-C", "repo", "worktree", "add", "-q", "--lock", "../kept"]);
+        git(&["-C", "repo", "worktree", "add", "-q", "--lock", "../gone"]);
+        let gone = dir.path().join("gone");
+        std::fs::remove_dir_all(&gone).unwrap();
+
+        let listed = list(&repo).await.unwrap();
+        let broken: Vec<PathBuf> = listed.into_iter().filter(|p| is_broken(p)).collect();
+        assert_eq!(broken.len(), 1);
+        assert!(broken[0].ends_with("gone"));
+
+        prune(&repo, &broken).await.unwrap();
+        let listed = list(&repo).await.unwrap();
+        assert_eq!(listed.len(), 2);
+        assert!(!listed.iter().any(|p| p.ends_with("gone")));
+    }
+
+    #[test]
+    fn find_branch_ignores_other_branches() {
+        assert_eq!(find_branch(PORCELAIN, "bar"), None);
+        assert_eq!(find_branch(PORCELAIN, "fo"), None);
+    }
+}
@@ -46,11 +46,28 @@ pub struct ContainerDetails {
     pub created: String,
     pub state: ContainerState,
     pub config: ContainerConfig,
+    #[serde(default)]
+    pub host_config: ContainerHostConfig,
     pub network_settings: NetworkSettings,
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub mounts: Vec<MountPoint>,
     #[serde(rename = "ExecIDs", default, deserialize_with = "null_as_default")]
     pub exec_ids: Vec<String>,
 }
 
+/// A bind mount or volume attached to a container.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct MountPoint {
+    /// Volume name; `None` for bind mounts.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Host path of the bind mount or volume.
+    #[serde(default)]
+    pub source: String,
+    pub destination: String,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct ContainerState {
@@ -87,6 +104,19 @@ pub struct ContainerConfig {
     pub env: Vec<String>,
     #[serde(default)]
     pub labels: IndexMap<String, String>,
+    /// User the container runs as; empty when the image default is used.
+    #[serde(default)]
+    pub user: String,
+}
+
+/// The subset of `HostConfig` we compare against devcontainer.json.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ContainerHostConfig {
+    #[serde(default)]
+    pub init: Option<bool>,
+    #[serde(default)]
+    pub privileged: bool,
 }
 
 impl ContainerConfig {
@@ -341,7 +371,10 @@ impl Docker {
         let mut url = self.url("containers/create");
         url.query_pairs_mut().append_pair("name", name);
 
-        labels.insert(MANAGED_LABEL.to_string(), "true".to_string());
+        // Callers may set their own value (e.g. a namespace); default to "true".
+        labels
+            .entry(MANAGED_LABEL.to_string())
+            .or_insert_with(|| "true".to_string());
 
         let body = CreateRequest {
             image,
@@ -427,4 +460,22 @@ mod tests {
         .expect("deserialize");
         assert_eq!(port.ip, Some("0.0.0.0".parse().unwrap()));
     }
+
+    #[test]
+    fn host_config_null_init_is_none() {
+        let host: ContainerHostConfig =
+            serde_json::from_str(r#"{"Init":null,"Privileged":true}"#).expect("deserialize");
+        assert_eq!(host.init, None);
+        assert!(host.privileged);
+    }
+
+    #[test]
+    fn mount_point_bind_has_no_name() {
+        let mount: MountPoint = serde_json::from_str(
+            r#"{"Type":"bind","Source":"/home/me/.aws","Destination":"/aws","RW":true}"#,
+        )
+        .expect("deserialize");
+        assert_eq!(mount.name, None);
+        assert_eq!(mount.source, "/home/me/.aws");
+    }
 }
@@ -13,6 +13,20 @@ pub struct ExecDetails {
     pub running: bool,
     /// Exit code; `None` while still running.
     pub exit_code: Option<i64>,
+    /// PID of the exec'd process, in the daemon's PID namespace; 0 once it has exited.
+    #[serde(default)]
+    pub pid: u32,
+    #[serde(default)]
+    pub process_config: ExecProcessConfig,
+}
+
+/// The command an exec runs.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecProcessConfig {
+    pub entrypoint: String,
+    #[serde(default)]
+    pub arguments: Vec<String>,
 }
 
 impl Docker {
@@ -0,0 +1,21 @@
+use serde::Deserialize;
+
+use crate::client::Docker;
+use crate::error::Result;
+use crate::request_ext::ReqwestExt;
+
+/// Subset of `GET /info`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SystemInfo {
+    /// CPUs available to the daemon.
+    #[serde(rename = "NCPU", default)]
+    pub ncpu: u32,
+}
+
+impl Docker {
+    /// `GET /info` — system-wide information about the daemon's host.
+    pub async fn info(&self) -> Result<SystemInfo> {
+        let url = self.url("info");
+        self.http().get(url).try_send().await
+    }
+}
@@ -13,6 +13,7 @@ mod events;
 mod exec;
 mod filter;
 mod images;
+mod info;
 mod request_ext;
 mod socket;
 mod stats;
@@ -25,14 +26,15 @@ pub mod test_support;
 pub use archive::{build_archive, build_single_file_tar};
 pub use client::Docker;
 pub use container::{
-    ContainerConfig, ContainerDetails, ContainerState, ContainerStatus, ContainerSummary,
-    EndpointSettings, NetworkSettings, Port, PortType,
+    ContainerConfig, ContainerDetails, ContainerHostConfig, ContainerState, ContainerStatus,
+    ContainerSummary, EndpointSettings, MountPoint, NetworkSettings, Port, PortType,
 };
 pub use error::{Error, Result};
 pub use events::{EventActor, EventMessage, EventsBuilder};
-pub use exec::ExecDetails;
+pub use exec::{ExecDetails, ExecProcessConfig};
 pub use filter::Filter;
 pub use images::ImageDetails;
+pub use info::SystemInfo;
 pub use socket::discover_socket;
 pub use stats::{ContainerStats, CpuStats, CpuUsage, MemoryStats};
 pub use types::ApiVersion;
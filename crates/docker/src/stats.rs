@@ -26,7 +26,8 @@ pub struct MemoryStats {
 pub struct CpuStats {
     #[serde(default)]
     pub cpu_usage: CpuUsage,
-    /// Host-wide cumulative CPU time, when reported.
+    /// Host-wide cumulative CPU time (ns), summed over cores, when reported. Some cgroup v2
+    /// setups leave it out.
     pub system_cpu_usage: Option<u64>,
     /// Online CPU count, when reported.
     pub online_cpus: Option<u32>,
@@ -37,6 +38,8 @@ pub struct CpuUsage {
     /// Cumulative container CPU time (ns).
     #[serde(default)]
     pub total_usage: u64,
+    /// Cumulative container CPU time per core (ns). Only reported on cgroup v1.
+    pub percpu_usage: Option<Vec<u64>>,
 }
 
 impl Docker {